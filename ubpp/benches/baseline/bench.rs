@@ -0,0 +1,38 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Dieselbe Messung wie `benches/interpreter.rs` für den Stand vor den Slots, in dem
+//! `eval_tokens` noch ohne Auflösen auswertet. Wird nur von `benches/vergleiche.sh` in einen
+//! Checkout dieses Stands kopiert und dort ausgeführt.
+
+mod common;
+
+use ubpplib::{
+    step2::parse_file,
+    step3::{eval_tokens, Console, Scope},
+};
+
+/// Beantwortet `e frog` immer gleich und verwirft die Ausgabe
+struct Input(&'static str);
+
+impl Console for Input {
+    fn print(&mut self, _line: &str) {}
+
+    fn input(&mut self, _prompt: &str) -> std::io::Result<String> {
+        Ok(format!("{}\n", self.0))
+    }
+}
+
+fn main() {
+    common::measure(|source| {
+        let tokens = parse_file(source).unwrap();
+        move |input| {
+            let mut scope = Scope::default();
+            scope.console = Some(Box::new(Input(input)));
+            eval_tokens(&tokens, &mut scope).unwrap();
+        }
+    });
+}
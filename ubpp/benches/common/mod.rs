@@ -0,0 +1,88 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Programme und Messung für `benches/interpreter.rs` und `benches/baseline/bench.rs`. Hängt nur
+//! von der Standardbibliothek ab, damit auch der alte Stand sie verwenden kann.
+
+use std::time::{Duration, Instant};
+
+/// So lange wird jedes Programm mindestens wiederholt
+const MEASUREMENT_TIME: Duration = Duration::from_secs(2);
+
+/// Name, Quelltext und Antwort auf `e frog`
+pub const PROGRAMS: &[(&str, &str, &str)] = &[
+    // Prüft bis zur Wurzel, d.h. gut 30'000 Schleifendurchläufe
+    ("example", include_str!("../../example.ubpp"), "1000000007"),
+    (
+        "zähle",
+        "loss summe = 0;
+        loss i = 0;
+        solang (i < 100000) {
+            summe = summe plus i mol i rest 7;
+            i = i plus 1;
+        }
+        summe",
+        "",
+    ),
+    (
+        "primzahle",
+        "loss anzahl = 0;
+        loss n = 2;
+        loss teiler = 2;
+        loss priim = wohr;
+        solang (n < 3000) {
+            teiler = 2;
+            priim = wohr;
+            solang (priim und teiler mol teiler <= n) {
+                falls (n rest teiler gliich isch wie 0) { priim = falsch; }
+                teiler = teiler plus 1;
+            }
+            falls (priim) { anzahl = anzahl plus 1; }
+            n = n plus 1;
+        }
+        anzahl",
+        "",
+    ),
+    (
+        "fibonacci",
+        "loss fib = funktion (n) {
+            falls (n < 2) { n } suscht { fib(n minus 1) plus fib(n minus 2) }
+        };
+        fib(18)",
+        "",
+    ),
+];
+
+/// Misst jedes Programm, das zum Filter auf der Kommandozeile passt. `prepare` erhält den
+/// Quelltext und gibt zurück, was ein einzelner Lauf mit der Antwort auf `e frog` macht.
+pub fn measure<F: FnMut(&'static str)>(mut prepare: impl FnMut(&'static str) -> F) {
+    // `cargo bench` übergibt `--bench`, alles andere ist ein Filter
+    let filter = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .unwrap_or_default();
+    for (name, source, input) in PROGRAMS {
+        if !name.contains(&filter) {
+            continue;
+        }
+        let mut run = prepare(source);
+        let mut runs = vec![];
+        let start = Instant::now();
+        while start.elapsed() < MEASUREMENT_TIME || runs.len() < 5 {
+            let time = Instant::now();
+            run(input);
+            runs.push(time.elapsed());
+        }
+        runs.sort();
+        println!(
+            "{:<12} {:>10.2?} pro Lauf (Median von {} Läufen, schnellster {:.2?})",
+            name,
+            runs[runs.len() / 2],
+            runs.len(),
+            runs[0]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Misst, wie schnell der Interpreter schleifenlastige Programme auswertet. Parsen und Auflösen
+//! zählen nicht dazu. Aufruf mit `cargo bench`, ein Filter wählt einzelne Programme aus, z.B.
+//! `cargo bench -- fibonacci`. `benches/vergleiche.sh` misst dieselben Programme zusätzlich mit
+//! dem Stand vor den Slots.
+
+mod common;
+
+use ubpplib::{
+    resolve::resolve,
+    step2::parse_file,
+    step3::{eval_program, Console, Scope},
+};
+
+/// Beantwortet `e frog` immer gleich und verwirft die Ausgabe
+struct Input(&'static str);
+
+impl Console for Input {
+    fn print(&mut self, _line: &str) {}
+
+    fn input(&mut self, _prompt: &str) -> std::io::Result<String> {
+        Ok(format!("{}\n", self.0))
+    }
+}
+
+fn main() {
+    common::measure(|source| {
+        let program = resolve(parse_file(source).unwrap(), &mut Scope::default()).unwrap();
+        move |input| {
+            let mut scope = Scope::default();
+            scope.console = Some(Box::new(Input(input)));
+            eval_program(&program, &mut scope).unwrap();
+        }
+    });
+}
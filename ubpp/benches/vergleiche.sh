@@ -0,0 +1,21 @@
+#!/bin/sh
+# Misst die Programme aus `benches/common` mit dem Interpreter vor den Slots und mit dem aktuellen.
+# Aufruf im Verzeichnis `ubpp`, z.B. `benches/vergleiche.sh` oder `benches/vergleiche.sh zähle`.
+set -e
+
+# Letzter Stand, in dem die Auswertung Variablen noch über ihren Namen sucht
+baseline=89ce8a4
+
+worktree=$(mktemp -d)
+git worktree add --quiet --detach "$worktree" "$baseline"
+trap 'git worktree remove --force "$worktree"' EXIT
+mkdir -p "$worktree/ubpp/benches"
+cp benches/baseline/bench.rs "$worktree/ubpp/benches/interpreter.rs"
+cp -r benches/common "$worktree/ubpp/benches/"
+printf '\n[[bench]]\nname = "interpreter"\nharness = false\n' >>"$worktree/ubpp/Cargo.toml"
+
+echo "Vorher ($baseline):"
+cargo bench --quiet --manifest-path "$worktree/ubpp/Cargo.toml" \
+    --target-dir target/vergleiche --bench interpreter -- "$@"
+echo "Jetzt:"
+cargo bench --quiet --bench interpreter -- "$@"
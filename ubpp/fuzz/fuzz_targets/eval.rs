@@ -0,0 +1,34 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Parst und führt beliebige Eingaben aus. Jeder Absturz ist ein Fehler: ungültige Programme
+//! müssen als [`ubpplib::step2::ParseError`] oder [`ubpplib::step1::RuntimeError`] enden.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ubpplib::{
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+/// Genug für kleine Programme, aber Endlosschleifen brechen schnell ab
+const STEP_LIMIT: u64 = 10_000;
+
+fuzz_target!(|source: &str| {
+    // `e frog` wartet auf die Standardeingabe. Dateien, auch für `bruuch`, sind ohne Freigabe
+    // verboten.
+    if source.contains("e frog") {
+        return;
+    }
+    let Ok(tokens) = parse_file(source) else {
+        return;
+    };
+    let mut scope = Scope::default();
+    scope.step_limit = Some(STEP_LIMIT);
+    scope.seed = Some(0);
+    let _ = eval_tokens(tokens, &mut scope);
+});
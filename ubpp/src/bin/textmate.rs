@@ -0,0 +1,29 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Schreibt `ubpp-syntax/syntaxes/ubpp.tmLanguage.json` neu, siehe [`ubpplib::textmate`]
+
+use std::path::Path;
+
+use ubpplib::textmate::textmate_grammar;
+
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let pest = root.join("ubpp.pest");
+    let grammar = std::fs::read_to_string(&pest)
+        .unwrap_or_else(|e| fail(format!("file: Cannot read `{}` ({})", pest.display(), e)));
+    let json = textmate_grammar(&grammar).unwrap_or_else(|e| fail(e));
+    let path = root.join("ubpp-syntax/syntaxes/ubpp.tmLanguage.json");
+    std::fs::write(&path, json)
+        .unwrap_or_else(|e| fail(format!("file: Cannot write `{}` ({})", path.display(), e)));
+    println!("{} written", path.display());
+}
+
+/// Gibt den Fehler wie `ubpp` als `error: art: meldig` aus und beendet das Programm
+fn fail(error: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", error);
+    std::process::exit(1);
+}
@@ -0,0 +1,151 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Zugriff auf Dateien. Standardmässig ist jeder Zugriff verboten, erlaubte Pfade werden mit
+//! [`Permissions`] freigegeben (z.B. `--allow-read=./data`).
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{arguments, Builtin};
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+use crate::step3::Scope;
+
+pub(super) static FUNCTIONS: &[Builtin] = &[
+    Builtin {
+        name: "zeilene",
+        function: zeilene,
+    },
+    Builtin {
+        name: "existiert",
+        function: existiert,
+    },
+];
+
+/// Verzeichnisse und Dateien, auf die ein Programm zugreifen darf
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+}
+
+impl Permissions {
+    /// Erlaubt das Lesen von `path` und allem darunter
+    pub fn allow_read(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.read.push(path.as_ref().canonicalize()?);
+        Ok(())
+    }
+
+    /// Erlaubt das Schreiben von `path` und allem darunter
+    pub fn allow_write(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.write.push(path.as_ref().canonicalize()?);
+        Ok(())
+    }
+
+    /// Gibt den aufgelösten Pfad zurück, falls er freigegeben ist
+    pub(crate) fn check(&self, path: &Path, write: bool) -> Result<PathBuf, RuntimeError> {
+        let resolved = resolve(path)?;
+        let allowed = if write { &self.write } else { &self.read };
+        if allowed.iter().any(|dir| resolved.starts_with(dir)) {
+            Ok(resolved)
+        } else {
+            Err(RuntimeError::new(
+                ErrorKind::Permission,
+                format!(
+                    "No permission to {} `{}`",
+                    if write { "write" } else { "read" },
+                    path.display()
+                ),
+            ))
+        }
+    }
+}
+
+/// Löst `..` und Symlinks auf. Die Datei und ihr Verzeichnis müssen nicht existieren.
+fn resolve(path: &Path) -> Result<PathBuf, RuntimeError> {
+    // Von einem Pfad, den es nicht gibt, wird der längste existierende Anfang aufgelöst. Im Rest
+    // darf kein `..` vorkommen, sonst wäre nicht klar, wo der Pfad landet.
+    let mut existing = path;
+    let mut missing = vec![];
+    loop {
+        if let Ok(mut resolved) = existing.canonicalize() {
+            resolved.extend(missing.iter().rev());
+            return Ok(resolved);
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(file_name)) => {
+                missing.push(file_name);
+                existing = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+            }
+            _ => return Err(file_error(path, "directory does not exist")),
+        }
+    }
+}
+
+fn file_error(path: &Path, error: impl std::fmt::Display) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::File,
+        format!("Cannot access `{}` ({})", path.display(), error),
+    )
+}
+
+/// `lies datei "x.txt"`
+pub fn read(global_scope: &Scope, path: &str) -> Result<String, RuntimeError> {
+    let path = global_scope
+        .permissions
+        .check(&global_scope.relative_path(path), false)?;
+    std::fs::read_to_string(&path).map_err(|e| file_error(&path, e))
+}
+
+/// `schriib "..." in datei "x.txt"` bzw. `häng "..." a datei "x.txt"`
+pub fn write(
+    global_scope: &Scope,
+    path: &str,
+    content: &str,
+    append: bool,
+) -> Result<(), RuntimeError> {
+    let path = global_scope
+        .permissions
+        .check(&global_scope.relative_path(path), true)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .map_err(|e| file_error(&path, e))?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| file_error(&path, e))
+}
+
+/// Liest eine Datei als Liste von Zeilen
+fn zeilene(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [path] = arguments("zeilene", args)?;
+    let content = read(global_scope, &global_scope.coerce_string(path)?)?;
+    let lines = content
+        .lines()
+        .map(|line| Atomic::String(line.to_string()))
+        .collect();
+    Ok(Atomic::List(Rc::new(lines)))
+}
+
+fn existiert(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [path] = arguments("existiert", args)?;
+    let path = global_scope.coerce_string(path)?;
+    // Auch ob es eine Datei nicht gibt, verrät `existiert` nur mit Freigabe
+    let path = global_scope
+        .permissions
+        .check(&global_scope.relative_path(&path), false)?;
+    Ok(Atomic::Bool(path.exists()))
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Umwandlung zwischen JSON und UB++ Werten für `vo json` und `als json`. Arrays werden zu
+//! Listen, Objekte zu Maps.
+
+use std::{collections::BTreeMap, rc::Rc, str::FromStr};
+
+use num_bigint::BigInt;
+use serde_json::{Number, Value};
+
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+
+/// `text vo json`
+pub fn parse_json(text: &str) -> Result<Atomic, RuntimeError> {
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| RuntimeError::new(ErrorKind::Json, format!("Invalid JSON ({})", e)))?;
+    Ok(from_value(value))
+}
+
+/// `wert als json`
+pub fn to_json(value: &Atomic) -> Result<String, RuntimeError> {
+    Ok(to_value(value)?.to_string())
+}
+
+fn from_value(value: Value) -> Atomic {
+    match value {
+        Value::Null => Atomic::Null,
+        Value::Bool(b) => Atomic::Bool(b),
+        Value::Number(n) => {
+            let n = n.to_string();
+            match n.parse::<BigInt>() {
+                Ok(i) => Atomic::Integer(i),
+                Err(_) => Atomic::Number(n.parse().unwrap_or(f64::NAN)),
+            }
+        }
+        Value::String(s) => Atomic::String(s),
+        Value::Array(values) => Atomic::List(Rc::new(values.into_iter().map(from_value).collect())),
+        Value::Object(fields) => Atomic::Map(Rc::new(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, from_value(value)))
+                .collect::<BTreeMap<_, _>>(),
+        )),
+    }
+}
+
+fn to_value(value: &Atomic) -> Result<Value, RuntimeError> {
+    let not_representable = || {
+        RuntimeError::new(
+            ErrorKind::Json,
+            format!("`{}` cannot be represented as JSON", value),
+        )
+    };
+    Ok(match value {
+        Atomic::Null => Value::Null,
+        Atomic::Bool(b) => Value::Bool(*b),
+        Atomic::Integer(i) => {
+            Value::Number(Number::from_str(&i.to_string()).map_err(|_| not_representable())?)
+        }
+        Atomic::Number(n) => Value::Number(Number::from_f64(*n).ok_or_else(not_representable)?),
+        Atomic::String(s) => Value::String(s.clone()),
+        Atomic::Date(_) => Value::String(value.to_string()),
+        Atomic::List(values) => {
+            Value::Array(values.iter().map(to_value).collect::<Result<_, _>>()?)
+        }
+        Atomic::Map(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), to_value(value)?)))
+                .collect::<Result<_, RuntimeError>>()?,
+        ),
+        Atomic::Record(record) => Value::Object(
+            record
+                .definition
+                .fields
+                .iter()
+                .zip(&record.values)
+                .map(|(field, value)| Ok((field.clone(), to_value(value)?)))
+                .collect::<Result<_, RuntimeError>>()?,
+        ),
+        // Wie bei serde: `"Leer"` oder `{"Kreis": {"radius": 2}}`
+        Atomic::VariantValue(value) if value.values.is_empty() => {
+            Value::String(value.variant.name.clone())
+        }
+        Atomic::VariantValue(value) => {
+            let fields = value
+                .variant
+                .fields
+                .iter()
+                .zip(&value.values)
+                .map(|(field, value)| Ok((field.clone(), to_value(value)?)))
+                .collect::<Result<_, RuntimeError>>()?;
+            Value::Object(
+                [(value.variant.name.clone(), Value::Object(fields))]
+                    .into_iter()
+                    .collect(),
+            )
+        }
+        Atomic::Error(_)
+        | Atomic::Module(_)
+        | Atomic::Struct(_)
+        | Atomic::Variant(_)
+        | Atomic::Builtin(_)
+        | Atomic::Function(_)
+        | Atomic::Interrupt => return Err(not_representable()),
+    })
+}
@@ -0,0 +1,208 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Mathematische Funktionen: `wurzel`, `abs`, `rund`, `min`, `sin`, ... und die Konstanten
+//! `pi` und `e`
+
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed};
+
+use super::{arguments, Builtin};
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+use crate::step3::{compare, not_a_number, Scope};
+
+pub(super) static FUNCTIONS: &[Builtin] = &[
+    Builtin {
+        name: "wurzel",
+        function: wurzel,
+    },
+    Builtin {
+        name: "abs",
+        function: abs,
+    },
+    Builtin {
+        name: "rund",
+        function: rund,
+    },
+    Builtin {
+        name: "abrunde",
+        function: abrunde,
+    },
+    Builtin {
+        name: "ufrunde",
+        function: ufrunde,
+    },
+    Builtin {
+        name: "min",
+        function: min,
+    },
+    Builtin {
+        name: "max",
+        function: max,
+    },
+    Builtin {
+        name: "sin",
+        function: sin,
+    },
+    Builtin {
+        name: "cos",
+        function: cos,
+    },
+    Builtin {
+        name: "tan",
+        function: tan,
+    },
+    Builtin {
+        name: "log",
+        function: log,
+    },
+    Builtin {
+        name: "exp",
+        function: exp,
+    },
+];
+
+pub(super) fn constant(name: &str) -> Option<Atomic> {
+    match name {
+        "pi" => Some(Atomic::Number(std::f64::consts::PI)),
+        "e" => Some(Atomic::Number(std::f64::consts::E)),
+        _ => None,
+    }
+}
+
+fn wurzel(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("wurzel", args)?;
+    let x = global_scope.coerce_num(x)?;
+    if x < 0.0 {
+        return Err(RuntimeError::new(
+            ErrorKind::Arithmetic,
+            format!("Cannot take the square root of `{}`", x),
+        ));
+    }
+    Ok(Atomic::Number(x.sqrt()))
+}
+
+fn abs(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("abs", args)?;
+    Ok(match global_scope.coerce_numeric(x)? {
+        Atomic::Integer(i) => Atomic::Integer(i.abs()),
+        Atomic::Number(n) => Atomic::Number(n.abs()),
+        other => return Err(not_a_number(other)),
+    })
+}
+
+fn rund(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("rund", args)?;
+    round_with(global_scope, x, f64::round)
+}
+
+fn abrunde(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("abrunde", args)?;
+    round_with(global_scope, x, f64::floor)
+}
+
+fn ufrunde(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("ufrunde", args)?;
+    round_with(global_scope, x, f64::ceil)
+}
+
+/// Rundet auf eine Ganzzahl, Ganzzahlen bleiben unverändert
+fn round_with(
+    global_scope: &Scope,
+    x: Atomic,
+    round: fn(f64) -> f64,
+) -> Result<Atomic, RuntimeError> {
+    match global_scope.coerce_numeric(x)? {
+        Atomic::Integer(i) => Ok(Atomic::Integer(i)),
+        Atomic::Number(n) => BigInt::from_f64(round(n))
+            .map(Atomic::Integer)
+            .ok_or_else(|| {
+                RuntimeError::new(ErrorKind::Arithmetic, format!("Cannot round `{}`", n))
+            }),
+        other => Err(not_a_number(other)),
+    }
+}
+
+fn min(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    extremum(global_scope, "min", args, Ordering::Less)
+}
+
+fn max(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    extremum(global_scope, "max", args, Ordering::Greater)
+}
+
+/// Sucht das kleinste bzw. grösste Argument, es braucht mindestens eines
+fn extremum(
+    global_scope: &Scope,
+    name: &str,
+    args: Vec<Atomic>,
+    wanted: Ordering,
+) -> Result<Atomic, RuntimeError> {
+    let mut args = args.into_iter();
+    let first = args.next().ok_or_else(|| {
+        RuntimeError::new(
+            ErrorKind::Argument,
+            format!("`{}` expects at least 1 argument", name),
+        )
+    })?;
+    let mut result = global_scope.coerce_numeric(first)?;
+    for arg in args {
+        let arg = global_scope.coerce_numeric(arg)?;
+        if compare(arg.clone(), result.clone())? == Some(wanted) {
+            result = arg;
+        }
+    }
+    Ok(result)
+}
+
+fn sin(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("sin", args)?;
+    Ok(Atomic::Number(global_scope.coerce_num(x)?.sin()))
+}
+
+fn cos(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("cos", args)?;
+    Ok(Atomic::Number(global_scope.coerce_num(x)?.cos()))
+}
+
+fn tan(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("tan", args)?;
+    Ok(Atomic::Number(global_scope.coerce_num(x)?.tan()))
+}
+
+/// Natürlicher Logarithmus, oder mit zweitem Argument zu einer beliebigen Basis
+fn log(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let (x, base) = match args.len() {
+        2 => {
+            let [x, base] = arguments("log", args)?;
+            (
+                global_scope.coerce_num(x)?,
+                Some(global_scope.coerce_num(base)?),
+            )
+        }
+        _ => {
+            let [x] = arguments("log", args)?;
+            (global_scope.coerce_num(x)?, None)
+        }
+    };
+    if x <= 0.0 || base.is_some_and(|base| base <= 0.0 || base == 1.0) {
+        return Err(RuntimeError::new(
+            ErrorKind::Arithmetic,
+            format!("Logarithm of `{}` is not defined", x),
+        ));
+    }
+    Ok(Atomic::Number(match base {
+        Some(base) => x.log(base),
+        None => x.ln(),
+    }))
+}
+
+fn exp(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [x] = arguments("exp", args)?;
+    Ok(Atomic::Number(global_scope.coerce_num(x)?.exp()))
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Eingebaute Funktionen und Konstanten. Sie liegen in der globalen Umgebung unter den
+//! Variablen des Programms und können von diesen überdeckt werden.
+
+mod files;
+mod json;
+mod math;
+mod random;
+mod strings;
+mod time;
+
+pub use files::{read, write, Permissions};
+pub use json::{parse_json, to_json};
+pub use random::random_number;
+pub use time::parse_date;
+
+use std::rc::Rc;
+
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+use crate::step3::Scope;
+
+/// Eine eingebaute Funktion, die mit den bereits ausgewerteten Argumenten aufgerufen wird
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub function: fn(&mut Scope, Vec<Atomic>) -> Result<Atomic, RuntimeError>,
+}
+
+static LIBRARIES: &[&[Builtin]] = &[
+    math::FUNCTIONS,
+    strings::FUNCTIONS,
+    files::FUNCTIONS,
+    time::FUNCTIONS,
+    random::FUNCTIONS,
+];
+
+/// Sucht eine eingebaute Funktion oder Konstante
+pub fn lookup(name: &str) -> Option<Atomic> {
+    if let Some(constant) = math::constant(name) {
+        return Some(constant);
+    }
+    LIBRARIES
+        .iter()
+        .flat_map(|library| library.iter())
+        .find(|builtin| builtin.name == name)
+        .map(Atomic::Builtin)
+}
+
+/// Prüft, dass genau `N` Argumente übergeben wurden
+fn arguments<const N: usize>(
+    name: &str,
+    arguments: Vec<Atomic>,
+) -> Result<[Atomic; N], RuntimeError> {
+    arguments.try_into().map_err(|arguments: Vec<Atomic>| {
+        RuntimeError::new(
+            ErrorKind::Argument,
+            format!(
+                "`{}` expects {} argument(s), got {}",
+                name,
+                N,
+                arguments.len()
+            ),
+        )
+    })
+}
+
+/// Prüft, dass `value` eine Liste ist
+fn expect_list(name: &str, value: Atomic) -> Result<Rc<Vec<Atomic>>, RuntimeError> {
+    match value {
+        Atomic::List(list) => Ok(list),
+        other => Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("`{}` expects a list, got `{}`", name, other),
+        )),
+    }
+}
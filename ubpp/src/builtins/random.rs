@@ -0,0 +1,67 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Zufall. Mit einem Seed (`--seed=42`) ist jeder Lauf reproduzierbar.
+
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use rand::{
+    seq::{IndexedRandom, SliceRandom},
+    RngExt,
+};
+
+use super::{arguments, expect_list, Builtin};
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+use crate::step3::Scope;
+
+pub(super) static FUNCTIONS: &[Builtin] = &[
+    Builtin {
+        name: "zuefallswahl",
+        function: zuefallswahl,
+    },
+    Builtin {
+        name: "mische",
+        function: mische,
+    },
+];
+
+/// `zuefallszahl zwüsche 1 und 100`, beide Grenzen sind eingeschlossen
+pub fn random_number(
+    global_scope: &mut Scope,
+    from: Atomic,
+    to: Atomic,
+) -> Result<Atomic, RuntimeError> {
+    let from = global_scope.coerce_integer(from)?;
+    let to = global_scope.coerce_integer(to)?;
+    match (from.to_i64(), to.to_i64()) {
+        (Some(from), Some(to)) if from <= to => Ok(Atomic::Integer(BigInt::from(
+            global_scope.rng().random_range(from..=to),
+        ))),
+        _ => Err(RuntimeError::new(
+            ErrorKind::Argument,
+            format!("Cannot pick a random number between {} and {}", from, to),
+        )),
+    }
+}
+
+/// Ein zufälliges Element der Liste
+fn zuefallswahl(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [list] = arguments("zuefallswahl", args)?;
+    let list = expect_list("zuefallswahl", list)?;
+    list.choose(global_scope.rng())
+        .cloned()
+        .ok_or_else(|| RuntimeError::new(ErrorKind::Argument, "Cannot choose from an empty list"))
+}
+
+/// Gibt eine gemischte Kopie der Liste zurück
+fn mische(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [list] = arguments("mische", args)?;
+    let mut list = expect_list("mische", list)?.as_ref().clone();
+    list.shuffle(global_scope.rng());
+    Ok(Atomic::List(Rc::new(list)))
+}
@@ -0,0 +1,289 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Funktionen für Zeichenketten. Ein Zeichen ist ein Graphem, d.h. `"ä"` hat immer die Länge 1,
+//! egal wie es kodiert ist. Indizes beginnen bei 0.
+
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{arguments, expect_list, Builtin};
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+use crate::step3::Scope;
+
+pub(super) static FUNCTIONS: &[Builtin] = &[
+    Builtin {
+        name: "längi",
+        function: laengi,
+    },
+    Builtin {
+        name: "zeiche",
+        function: zeiche,
+    },
+    Builtin {
+        name: "usschnitt",
+        function: usschnitt,
+    },
+    Builtin {
+        name: "gross",
+        function: gross,
+    },
+    Builtin {
+        name: "chlii",
+        function: chlii,
+    },
+    Builtin {
+        name: "trimm",
+        function: trimm,
+    },
+    Builtin {
+        name: "teile",
+        function: teile,
+    },
+    Builtin {
+        name: "verbinde",
+        function: verbinde,
+    },
+    Builtin {
+        name: "element",
+        function: element,
+    },
+    Builtin {
+        name: "schlüssel",
+        function: schluessel,
+    },
+    Builtin {
+        name: "enthaltet",
+        function: enthaltet,
+    },
+    Builtin {
+        name: "startetMit",
+        function: startet_mit,
+    },
+    Builtin {
+        name: "endetMit",
+        function: endet_mit,
+    },
+    Builtin {
+        name: "ersetz",
+        function: ersetz,
+    },
+    Builtin {
+        name: "wiederhol",
+        function: wiederhol,
+    },
+];
+
+/// Anzahl Zeichen einer Zeichenkette oder Anzahl Elemente einer Liste bzw. Map
+fn laengi(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [value] = arguments("längi", args)?;
+    let length = match value {
+        Atomic::List(list) => list.len(),
+        Atomic::Map(map) => map.len(),
+        value => global_scope.coerce_string(value)?.graphemes(true).count(),
+    };
+    Ok(Atomic::Integer(BigInt::from(length)))
+}
+
+fn zeiche(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, i] = arguments("zeiche", args)?;
+    let text = global_scope.coerce_string(text)?;
+    let graphemes = text.graphemes(true).collect::<Vec<_>>();
+    let i = index(global_scope, i, graphemes.len())?;
+    Ok(Atomic::String(graphemes[i].to_string()))
+}
+
+/// `usschnitt(text, start)` bis zum Ende oder `usschnitt(text, start, längi)`
+fn usschnitt(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let (text, start, length) = match args.len() {
+        3 => {
+            let [text, start, length] = arguments("usschnitt", args)?;
+            (text, start, Some(length))
+        }
+        _ => {
+            let [text, start] = arguments("usschnitt", args)?;
+            (text, start, None)
+        }
+    };
+    let text = global_scope.coerce_string(text)?;
+    let graphemes = text.graphemes(true).collect::<Vec<_>>();
+    let len = graphemes.len();
+    // Der Start darf direkt hinter dem letzten Zeichen liegen, das ergibt eine leere Zeichenkette
+    let start = checked_index(global_scope, start, len, |start| start <= len)?;
+    let end = match length {
+        Some(length) => {
+            start + checked_index(global_scope, length, len, |length| length <= len - start)?
+        }
+        None => len,
+    };
+    Ok(Atomic::String(graphemes[start..end].concat()))
+}
+
+fn gross(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text] = arguments("gross", args)?;
+    Ok(Atomic::String(
+        global_scope.coerce_string(text)?.to_uppercase(),
+    ))
+}
+
+fn chlii(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text] = arguments("chlii", args)?;
+    Ok(Atomic::String(
+        global_scope.coerce_string(text)?.to_lowercase(),
+    ))
+}
+
+fn trimm(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text] = arguments("trimm", args)?;
+    Ok(Atomic::String(
+        global_scope.coerce_string(text)?.trim().to_string(),
+    ))
+}
+
+/// Teilt die Zeichenkette beim Trenner auf. Mit leerem Trenner entsteht eine Liste der Zeichen.
+fn teile(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, separator] = arguments("teile", args)?;
+    let text = global_scope.coerce_string(text)?;
+    let separator = global_scope.coerce_string(separator)?;
+    let parts: Vec<Atomic> = if separator.is_empty() {
+        text.graphemes(true)
+            .map(|part| Atomic::String(part.to_string()))
+            .collect()
+    } else {
+        text.split(separator.as_str())
+            .map(|part| Atomic::String(part.to_string()))
+            .collect()
+    };
+    Ok(Atomic::List(Rc::new(parts)))
+}
+
+fn verbinde(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [list, separator] = arguments("verbinde", args)?;
+    let list = expect_list("verbinde", list)?;
+    let separator = global_scope.coerce_string(separator)?;
+    let parts = list
+        .iter()
+        .map(|value| global_scope.coerce_string(value.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Atomic::String(parts.join(&separator)))
+}
+
+/// Element einer Liste nach Index oder einer Map nach Schlüssel
+fn element(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [list, i] = arguments("element", args)?;
+    if let Atomic::Map(map) = list {
+        let key = global_scope.coerce_string(i)?;
+        return map.get(&key).cloned().ok_or_else(|| {
+            RuntimeError::new(ErrorKind::Index, format!("Key `{}` does not exist", key))
+        });
+    }
+    let list = expect_list("element", list)?;
+    let i = index(global_scope, i, list.len())?;
+    Ok(list[i].clone())
+}
+
+/// Die Schlüssel einer Map als Liste
+fn schluessel(_: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [map] = arguments("schlüssel", args)?;
+    match map {
+        Atomic::Map(map) => Ok(Atomic::List(Rc::new(
+            map.keys().map(|key| Atomic::String(key.clone())).collect(),
+        ))),
+        other => Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("`schlüssel` expects a map, got `{}`", other),
+        )),
+    }
+}
+
+fn enthaltet(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, part] = arguments("enthaltet", args)?;
+    Ok(Atomic::Bool(
+        global_scope
+            .coerce_string(text)?
+            .contains(global_scope.coerce_string(part)?.as_str()),
+    ))
+}
+
+fn startet_mit(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, prefix] = arguments("startetMit", args)?;
+    Ok(Atomic::Bool(
+        global_scope
+            .coerce_string(text)?
+            .starts_with(global_scope.coerce_string(prefix)?.as_str()),
+    ))
+}
+
+fn endet_mit(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, suffix] = arguments("endetMit", args)?;
+    Ok(Atomic::Bool(
+        global_scope
+            .coerce_string(text)?
+            .ends_with(global_scope.coerce_string(suffix)?.as_str()),
+    ))
+}
+
+fn ersetz(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, from, to] = arguments("ersetz", args)?;
+    let from = global_scope.coerce_string(from)?;
+    if from.is_empty() {
+        return Err(RuntimeError::new(
+            ErrorKind::Argument,
+            "`ersetz` cannot replace an empty string",
+        ));
+    }
+    Ok(Atomic::String(
+        global_scope
+            .coerce_string(text)?
+            .replace(&from, &global_scope.coerce_string(to)?),
+    ))
+}
+
+/// Längste Zeichenkette in Bytes, die `wiederhol` erstellt, statt den Speicher zu sprengen
+const MAX_REPEAT_LENGTH: usize = 1 << 30;
+
+fn wiederhol(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text, count] = arguments("wiederhol", args)?;
+    let text = global_scope.coerce_string(text)?;
+    let count = global_scope.coerce_integer(count)?;
+    let count = count
+        .to_usize()
+        .filter(|count| {
+            text.len()
+                .checked_mul(*count)
+                .is_some_and(|length| length <= MAX_REPEAT_LENGTH)
+        })
+        .ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::Argument,
+                format!("Cannot repeat a string `{}` times", count),
+            )
+        })?;
+    Ok(Atomic::String(text.repeat(count)))
+}
+
+/// Prüft, dass `i` ein gültiger Index für eine Länge von `length` ist
+fn index(global_scope: &Scope, i: Atomic, length: usize) -> Result<usize, RuntimeError> {
+    checked_index(global_scope, i, length, |i| i < length)
+}
+
+fn checked_index(
+    global_scope: &Scope,
+    i: Atomic,
+    length: usize,
+    valid: impl Fn(usize) -> bool,
+) -> Result<usize, RuntimeError> {
+    let i = global_scope.coerce_integer(i)?;
+    i.to_usize().filter(|i| valid(*i)).ok_or_else(|| {
+        RuntimeError::new(
+            ErrorKind::Index,
+            format!("`{}` is out of range (length {})", i, length),
+        )
+    })
+}
@@ -0,0 +1,218 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Datum und Zeit im Schweizer Format, z.B. `datum("18.10.2026")`
+
+use std::fmt::Write;
+
+use chrono::{format::StrftimeItems, Datelike, Duration, Local, NaiveDate, NaiveDateTime};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use super::{arguments, Builtin};
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
+use crate::step3::Scope;
+
+pub(super) static FUNCTIONS: &[Builtin] = &[
+    Builtin {
+        name: "jetzt",
+        function: jetzt,
+    },
+    Builtin {
+        name: "hüt",
+        function: huet,
+    },
+    Builtin {
+        name: "datum",
+        function: datum,
+    },
+    Builtin {
+        name: "tägDezue",
+        function: taeg_dezue,
+    },
+    Builtin {
+        name: "tägZwüsche",
+        function: taeg_zwuesche,
+    },
+    Builtin {
+        name: "wuchetag",
+        function: wuchetag,
+    },
+    Builtin {
+        name: "formatiere",
+        function: formatiere,
+    },
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Mäntig",
+    "Ziischtig",
+    "Mittwuch",
+    "Dunnschtig",
+    "Friitig",
+    "Samschtig",
+    "Sunntig",
+];
+
+const MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "Auguscht",
+    "Septämber",
+    "Oktober",
+    "Novämber",
+    "Dezämber",
+];
+
+/// Liest `18.10.2026`, optional mit Uhrzeit `18.10.2026 14:30` oder `18.10.2026 14:30:15`
+pub fn parse_date(text: &str) -> Result<NaiveDateTime, RuntimeError> {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text, "%d.%m.%Y")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%d.%m.%Y %H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%d.%m.%Y %H:%M"))
+        .map_err(|e| {
+            RuntimeError::new(ErrorKind::Type, format!("`{}` is not a date ({})", text, e))
+        })
+}
+
+fn now(global_scope: &Scope) -> NaiveDateTime {
+    global_scope
+        .now
+        .unwrap_or_else(|| Local::now().naive_local())
+}
+
+/// Ein Datum oder ein Text wie `18.10.2026`, im strikten Modus nur ein Datum
+fn expect_date(
+    global_scope: &Scope,
+    name: &str,
+    value: Atomic,
+) -> Result<NaiveDateTime, RuntimeError> {
+    global_scope.check_strict(&value, "date", matches!(value, Atomic::Date(_)))?;
+    match value {
+        Atomic::Date(date) => Ok(date),
+        Atomic::String(s) => parse_date(&s),
+        other => Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("`{}` expects a date, got `{}`", name, other),
+        )),
+    }
+}
+
+fn jetzt(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [] = arguments("jetzt", args)?;
+    Ok(Atomic::Date(now(global_scope)))
+}
+
+/// Das heutige Datum ohne Uhrzeit
+fn huet(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [] = arguments("hüt", args)?;
+    let today = now(global_scope).date().and_hms_opt(0, 0, 0);
+    Ok(Atomic::Date(today.unwrap_or_default()))
+}
+
+fn datum(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [text] = arguments("datum", args)?;
+    Ok(Atomic::Date(parse_date(
+        &global_scope.coerce_string(text)?,
+    )?))
+}
+
+fn taeg_dezue(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [date, days] = arguments("tägDezue", args)?;
+    let date = expect_date(global_scope, "tägDezue", date)?;
+    let days = global_scope.coerce_integer(days)?;
+    days.to_i64()
+        .and_then(Duration::try_days)
+        .and_then(|days| date.checked_add_signed(days))
+        .map(Atomic::Date)
+        .ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::Arithmetic,
+                format!("Cannot add {} days to `{}`", days, Atomic::Date(date)),
+            )
+        })
+}
+
+/// Anzahl ganzer Tage vom ersten bis zum zweiten Datum
+fn taeg_zwuesche(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [from, to] = arguments("tägZwüsche", args)?;
+    let from = expect_date(global_scope, "tägZwüsche", from)?;
+    let to = expect_date(global_scope, "tägZwüsche", to)?;
+    Ok(Atomic::Integer(BigInt::from((to - from).num_days())))
+}
+
+fn wuchetag(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [date] = arguments("wuchetag", args)?;
+    let date = expect_date(global_scope, "wuchetag", date)?;
+    let weekday = WEEKDAYS[date.weekday().num_days_from_monday() as usize];
+    Ok(Atomic::String(weekday.to_string()))
+}
+
+/// Formatiert mit `strftime` Platzhaltern, z.B. `formatiere(jetzt(), "%H:%M")`. Wochentage und
+/// Monate heissen wie bei [`wuchetag`], z.B. `%A, %e. %B` ergibt `Sunntig, 18. Oktober`.
+fn formatiere(global_scope: &mut Scope, args: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    let [date, format] = arguments("formatiere", args)?;
+    let date = expect_date(global_scope, "formatiere", date)?;
+    let format = swiss_names(&global_scope.coerce_string(format)?, &date);
+    // Ungültige Platzhalter und solche für Zeitzonen, die ein Datum nicht hat, ergeben beim
+    // Formatieren einen Fehler, den `to_string` in einen Panic verwandeln würde
+    let mut formatted = String::new();
+    write!(
+        formatted,
+        "{}",
+        date.format_with_items(StrftimeItems::new(&format))
+    )
+    .map_err(|_| {
+        RuntimeError::new(
+            ErrorKind::Argument,
+            format!("`{}` is not a valid date format", format),
+        )
+    })?;
+    Ok(Atomic::String(formatted))
+}
+
+/// Ersetzt die Platzhalter, für die `chrono` englische Namen einsetzt: `%A` und `%a` für den
+/// Wochentag, `%B`, `%b` und `%h` für den Monat sowie `%c` und `%v`, die solche enthalten
+fn swiss_names(format: &str, date: &NaiveDateTime) -> String {
+    let weekday = WEEKDAYS[date.weekday().num_days_from_monday() as usize];
+    let month = MONTHS[date.month0() as usize];
+    let mut replaced = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        replaced.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // Das Auffüllen mit `%-a`, `%_a` oder `%0a` spielt für Namen keine Rolle
+        let modifier = rest[1..].starts_with(['-', '_', '0']) as usize;
+        let Some(spec) = rest[1 + modifier..].chars().next() else {
+            break;
+        };
+        let name = match spec {
+            'A' => weekday.to_string(),
+            'a' => weekday.chars().take(2).collect(),
+            'B' => month.to_string(),
+            'b' | 'h' => month.chars().take(3).collect(),
+            'c' => swiss_names("%a %b %e %H:%M:%S %Y", date),
+            'v' => swiss_names("%e-%b-%Y", date),
+            // Auch `%%`, damit das zweite `%` keinen Platzhalter beginnt
+            _ => {
+                let len = 1 + modifier + spec.len_utf8();
+                replaced.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+        };
+        replaced.push_str(&name);
+        rest = &rest[1 + modifier + 1..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
@@ -0,0 +1,371 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Die Grammatik kennt nur die Schlüsselwörter auf Züritüütsch. Andere Dialekte werden vor dem
+//! Parsen Wort für Wort in diese Schlüsselwörter übersetzt, dadurch entsteht für alle Dialekte
+//! derselbe AST. Die Schlüsselwörter auf Züritüütsch gelten darum in jedem Dialekt und sind dort
+//! auch als Namen reserviert, z.B. kann eine Variable auf Englisch nicht `falls` heissen.
+
+use std::{ops::Range, str::FromStr};
+
+use pest::{
+    error::{Error, InputLocation},
+    Position,
+};
+
+use crate::{
+    step1::Token,
+    step2::{parse_file_with_nesting, ParseError},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Zurich,
+    Bernese,
+    Basel,
+    HighGerman,
+    English,
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "züritüütsch" | "zürich" | "zh" => Ok(Dialect::Zurich),
+            "bärndütsch" | "bern" | "be" => Ok(Dialect::Bernese),
+            "baseldytsch" | "basel" | "bs" => Ok(Dialect::Basel),
+            "hochdeutsch" | "deutsch" | "de" => Ok(Dialect::HighGerman),
+            "english" | "englisch" | "en" => Ok(Dialect::English),
+            other => Err(format!("Unknown dialect `{}`", other)),
+        }
+    }
+}
+
+impl Dialect {
+    /// Liest den Dialekt aus einem Kommentar `/* dialekt: bärndütsch */` am Anfang der Datei
+    pub fn from_header(source: &str) -> Option<Result<Dialect, String>> {
+        let comment = source.trim_start().strip_prefix("/*")?;
+        let comment = &comment[..comment.find("*/")?];
+        let name = comment.trim().strip_prefix("dialekt:")?;
+        Some(name.parse())
+    }
+
+    /// Übersetzt die Schlüsselwörter des Dialekts nach Züritüütsch. Zeichenketten und
+    /// Kommentare bleiben unverändert.
+    pub fn translate(&self, source: &str) -> String {
+        self.translate_mapped(source).0
+    }
+
+    /// Übersetzt und parst `source`. Die Position in einem Fehler bezieht sich auf `source`, so
+    /// dass die Meldung die Zeile zeigt, wie sie im Programm steht.
+    pub fn parse(&self, source: &str, max_nesting: usize) -> Result<Vec<Token>, ParseError> {
+        let (translated, replacements) = self.translate_mapped(source);
+        parse_file_with_nesting(&translated, max_nesting).map_err(|error| {
+            if replacements.is_empty() {
+                return error;
+            }
+            let position = |offset| {
+                Position::new(source, original_offset(&replacements, offset))
+                    .unwrap_or_else(|| Position::from_start(source))
+            };
+            Box::new(match error.location {
+                InputLocation::Pos(offset) => Error::new_from_pos(error.variant, position(offset)),
+                InputLocation::Span((start, end)) => {
+                    Error::new_from_span(error.variant, position(start).span(&position(end)))
+                }
+            })
+        })
+    }
+
+    /// Wie [`Dialect::translate`], gibt zusätzlich die ersetzten Stellen zurück
+    fn translate_mapped(&self, source: &str) -> (String, Vec<Replacement>) {
+        let mut keywords = self.keywords().to_vec();
+        // Längere Phrasen zuerst, damit z.B. `is less than` vor `is` gefunden wird
+        keywords.sort_by_key(|(phrase, _)| std::cmp::Reverse(phrase.len()));
+
+        let mut translated = String::with_capacity(source.len());
+        let mut replacements = vec![];
+        let mut rest = source;
+        while let Some(c) = rest.chars().next() {
+            let skipped = if c == '"' {
+                string_len(rest)
+            } else if rest.starts_with("/*") {
+                rest.find("*/").map(|end| end + 2).unwrap_or(rest.len())
+            } else if is_word_char(c) {
+                if let Some((len, keyword)) = keywords
+                    .iter()
+                    .find_map(|(phrase, keyword)| Some((match_phrase(rest, phrase)?, keyword)))
+                {
+                    let original = source.len() - rest.len();
+                    replacements.push(Replacement {
+                        translated: translated.len()..translated.len() + keyword.len(),
+                        original: original..original + len,
+                    });
+                    translated.push_str(keyword);
+                    rest = &rest[len..];
+                    continue;
+                }
+                rest.find(|c| !is_word_char(c)).unwrap_or(rest.len())
+            } else {
+                c.len_utf8()
+            };
+            translated.push_str(&rest[..skipped]);
+            rest = &rest[skipped..];
+        }
+        (translated, replacements)
+    }
+
+    /// Paare von (Dialekt, Züritüütsch)
+    fn keywords(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Dialect::Zurich => &[],
+            Dialect::Bernese => BERNESE,
+            Dialect::Basel => BASEL,
+            Dialect::HighGerman => HIGH_GERMAN,
+            Dialect::English => ENGLISH,
+        }
+    }
+}
+
+/// Ein übersetztes Schlüsselwort und die Phrase, für die es steht, als Bereiche in Bytes
+struct Replacement {
+    translated: Range<usize>,
+    original: Range<usize>,
+}
+
+/// Die Position im Original zu einer Position in der Übersetzung. Innerhalb eines Schlüsselworts
+/// ist das der Anfang der Phrase, dahinter verschiebt sich alles um den Unterschied der Längen.
+fn original_offset(replacements: &[Replacement], offset: usize) -> usize {
+    let before = replacements.partition_point(|r| r.translated.start <= offset);
+    match before.checked_sub(1).map(|i| &replacements[i]) {
+        None => offset,
+        Some(r) if offset < r.translated.end => r.original.start,
+        Some(r) => offset - r.translated.end + r.original.end,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Länge der Zeichenkette am Anfang von `rest` mit beiden Anführungszeichen. Ein `\"` beendet
+/// sie nicht, und ohne Ende reicht sie bis zum Schluss.
+fn string_len(rest: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+/// Gibt die Länge zurück, falls `source` mit der Phrase beginnt. Die Wörter der Phrase dürfen
+/// durch beliebig viele Leerschläge und Tabs getrennt sein, aber nicht durch einen Zeilenumbruch,
+/// damit die Übersetzung dieselben Zeilen hat wie das Original.
+fn match_phrase(source: &str, phrase: &str) -> Option<usize> {
+    let mut rest = source;
+    for (i, word) in phrase.split(' ').enumerate() {
+        if i > 0 {
+            let trimmed = rest.trim_start_matches([' ', '\t']);
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            rest = trimmed;
+        }
+        rest = rest.strip_prefix(word)?;
+    }
+    if rest.chars().next().is_some_and(is_word_char) {
+        return None;
+    }
+    Some(source.len() - rest.len())
+}
+
+const BERNESE: &[(&str, &str)] = &[
+    ("glych isch wie", "gliich isch wie"),
+    ("isch glych wie", "gliich isch wie"),
+    ("unglych isch wie", "ungliich isch wie"),
+    ("isch unglych wie", "ungliich isch wie"),
+    ("isch chliner aus", "isch kliiner als"),
+    (
+        "isch chliner oder glych wie",
+        "isch kliiner oder gliich wie",
+    ),
+    ("isch grösser aus", "isch grösser als"),
+    (
+        "isch grösser oder glych wie",
+        "isch grösser oder gliich wie",
+    ),
+    ("oder", "odr"),
+    ("nid", "nöd"),
+    ("we", "falls"),
+    ("süsch", "suscht"),
+    ("gib uus", "gib us"),
+    ("e frag", "e frog"),
+    ("la", "loss"),
+    ("mit em wärt", "mit em wert"),
+    ("wahr", "wohr"),
+    ("aus", "als"),
+    ("Zau", "Zahl"),
+    ("Ganzzau", "Ganzzahl"),
+    ("Kommazau", "Kommazahl"),
+    ("Zeichechetti", "Zeicheketti"),
+    ("Wahrheitswärt", "Wohretswärt"),
+    ("mau", "mol"),
+    ("lue ob", "lueg ob"),
+    ("angers", "susch"),
+    ("fah", "fang"),
+    ("schmeiss", "wirf"),
+    ("schryb", "schriib"),
+];
+
+const BASEL: &[(&str, &str)] = &[
+    ("glych isch wie", "gliich isch wie"),
+    ("isch glych wie", "gliich isch wie"),
+    ("unglych isch wie", "ungliich isch wie"),
+    ("isch unglych wie", "ungliich isch wie"),
+    ("isch glainer als", "isch kliiner als"),
+    (
+        "isch glainer oder glych wie",
+        "isch kliiner oder gliich wie",
+    ),
+    ("isch gresser als", "isch grösser als"),
+    (
+        "isch gresser oder glych wie",
+        "isch grösser oder gliich wie",
+    ),
+    ("oder", "odr"),
+    ("nit", "nöd"),
+    ("wenn", "falls"),
+    ("sunscht", "suscht"),
+    ("gib uus", "gib us"),
+    ("woor", "wohr"),
+    ("Zaichekette", "Zeicheketti"),
+    ("Woorhaitswärt", "Wohretswärt"),
+    ("mool", "mol"),
+    ("anderscht", "susch"),
+    ("schmeiss", "wirf"),
+];
+
+const HIGH_GERMAN: &[(&str, &str)] = &[
+    ("ist gleich wie", "gliich isch wie"),
+    ("gleich ist wie", "gliich isch wie"),
+    ("ist ungleich wie", "ungliich isch wie"),
+    ("ungleich ist wie", "ungliich isch wie"),
+    ("ist kleiner als", "isch kliiner als"),
+    (
+        "ist kleiner oder gleich wie",
+        "isch kliiner oder gliich wie",
+    ),
+    ("ist größer als", "isch grösser als"),
+    ("ist grösser als", "isch grösser als"),
+    ("ist größer oder gleich wie", "isch grösser oder gliich wie"),
+    (
+        "ist grösser oder gleich wie",
+        "isch grösser oder gliich wie",
+    ),
+    ("oder", "odr"),
+    ("nicht", "nöd"),
+    ("wenn", "falls"),
+    ("sonst", "suscht"),
+    ("solange", "solang"),
+    ("mache", "mach"),
+    ("halt", "stop"),
+    ("gib aus", "gib us"),
+    ("frage", "e frog"),
+    ("lass", "loss"),
+    ("definiere eine Variable die", "definier e variable wo"),
+    ("definiere eine Struktur die", "definier e struktur wo"),
+    ("definiere eine Auswahl die", "definier e uswahl wo"),
+    ("heißt", "heisst"),
+    ("mit dem Wert", "mit em wert"),
+    ("ist", "isch"),
+    ("wahr", "wohr"),
+    ("Zeichenkette", "Zeicheketti"),
+    ("Wahrheitswert", "Wohretswärt"),
+    ("mal", "mol"),
+    ("prüfe ob", "lueg ob"),
+    ("ansonsten", "susch"),
+    ("versuche", "probier"),
+    ("fange", "fang"),
+    ("lies Datei", "lies datei"),
+    ("schreibe", "schriib"),
+    ("in Datei", "in datei"),
+    ("hänge", "häng"),
+    ("an Datei", "a datei"),
+    ("von json", "vo json"),
+    ("Zufallszahl zwischen", "zuefallszahl zwüsche"),
+    ("erwarte", "erwart"),
+    ("Funktion", "funktion"),
+    ("der", "dr"),
+    ("die", "d"),
+    ("das", "de"),
+];
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("is equal to", "gliich isch wie"),
+    ("equals", "gliich isch wie"),
+    ("is not equal to", "ungliich isch wie"),
+    ("is less than", "isch kliiner als"),
+    ("is less than or equal to", "isch kliiner oder gliich wie"),
+    ("is greater than", "isch grösser als"),
+    (
+        "is greater than or equal to",
+        "isch grösser oder gliich wie",
+    ),
+    ("and", "und"),
+    ("or", "odr"),
+    ("not", "nöd"),
+    ("if", "falls"),
+    ("else", "suscht"),
+    ("while", "solang"),
+    ("do", "mach"),
+    ("break", "stop"),
+    ("print", "gib us"),
+    ("ask", "e frog"),
+    ("let", "loss"),
+    ("define a variable named", "definier e variable wo"),
+    ("define a struct named", "definier e struktur wo"),
+    ("with fields", "heisst mit"),
+    ("define an enum named", "definier e uswahl wo"),
+    ("with variants", "heisst"),
+    ("with value", "heisst mit em wert"),
+    ("is", "isch"),
+    ("true", "wohr"),
+    ("false", "falsch"),
+    ("Number", "Zahl"),
+    ("Integer", "Ganzzahl"),
+    ("Float", "Kommazahl"),
+    ("String", "Zeicheketti"),
+    ("Boolean", "Wohretswärt"),
+    ("as", "als"),
+    ("times", "mol"),
+    ("divided by", "durch"),
+    ("mod", "rest"),
+    ("to the power of", "hoch"),
+    ("match", "lueg ob"),
+    ("otherwise", "susch"),
+    ("to", "bis"),
+    ("try", "probier"),
+    ("catch", "fang"),
+    ("throw", "wirf"),
+    ("read file", "lies datei"),
+    ("write", "schriib"),
+    ("into file", "in datei"),
+    ("append", "häng"),
+    ("to file", "a datei"),
+    ("from json", "vo json"),
+    ("random number between", "zuefallszahl zwüsche"),
+    ("test", "teste"),
+    ("expect", "erwart"),
+    ("function", "funktion"),
+    ("the", "de"),
+];
@@ -0,0 +1,158 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Nachrichten des Jupyter-Protokolls, siehe
+//! <https://jupyter-client.readthedocs.io/en/stable/messaging.html>
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use super::zmtp::Frames;
+
+pub const PROTOCOL_VERSION: &str = "5.3";
+
+/// Trennt die Identitäten für das Routing vom Rest der Nachricht
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Routing-Identitäten bzw. das Thema bei IOPub
+    pub identities: Vec<Vec<u8>>,
+    pub header: Value,
+    pub parent_header: Value,
+    pub metadata: Value,
+    pub content: Value,
+}
+
+impl Message {
+    pub fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+}
+
+/// Erstellt, signiert und prüft Nachrichten mit dem Schlüssel aus der Verbindungsdatei
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    key: Vec<u8>,
+}
+
+fn random_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+impl Session {
+    /// Mit leerem Schlüssel werden Nachrichten nicht signiert
+    pub fn new(key: &str) -> Self {
+        Session {
+            id: random_id(),
+            key: key.as_bytes().to_vec(),
+        }
+    }
+
+    /// Eine neue Nachricht, bei einer Antwort mit den Identitäten der Anfrage
+    pub fn message(&self, msg_type: &str, content: Value, parent: Option<&Message>) -> Message {
+        Message {
+            identities: parent.map(|p| p.identities.clone()).unwrap_or_default(),
+            header: json!({
+                "msg_id": random_id(),
+                "session": self.id,
+                "username": "ubpp",
+                "date": Utc::now().to_rfc3339(),
+                "msg_type": msg_type,
+                "version": PROTOCOL_VERSION,
+            }),
+            parent_header: parent.map_or_else(|| json!({}), |p| p.header.clone()),
+            metadata: json!({}),
+            content,
+        }
+    }
+
+    /// Hex-codierte Signatur über Header, Parent-Header, Metadaten und Inhalt
+    pub fn signature(&self, parts: &[Vec<u8>]) -> Vec<u8> {
+        if self.key.is_empty() {
+            return vec![];
+        }
+        self.mac(parts)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .flat_map(|byte| format!("{:02x}", byte).into_bytes())
+            .collect()
+    }
+
+    fn mac(&self, parts: &[Vec<u8>]) -> Hmac<Sha256> {
+        // HMAC akzeptiert Schlüssel jeder Länge
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key");
+        for part in parts {
+            mac.update(part);
+        }
+        mac
+    }
+
+    /// Vergleicht in konstanter Zeit, damit sich eine Signatur nicht Byte für Byte erraten lässt
+    fn verify(&self, signature: &[u8], parts: &[Vec<u8>]) -> bool {
+        if self.key.is_empty() {
+            return signature.is_empty();
+        }
+        decode_hex(signature)
+            .is_some_and(|signature| self.mac(parts).verify_slice(&signature).is_ok())
+    }
+
+    pub fn encode(&self, message: &Message) -> Frames {
+        let parts = [
+            &message.header,
+            &message.parent_header,
+            &message.metadata,
+            &message.content,
+        ]
+        .map(|value| value.to_string().into_bytes());
+        let mut frames = message.identities.clone();
+        frames.push(DELIMITER.to_vec());
+        frames.push(self.signature(&parts));
+        frames.extend(parts);
+        frames
+    }
+
+    pub fn decode(&self, mut frames: Frames) -> Result<Message, String> {
+        let delimiter = frames
+            .iter()
+            .position(|frame| frame == DELIMITER)
+            .ok_or("Message without delimiter")?;
+        let rest = frames.split_off(delimiter);
+        // Nach dem Trenner folgen Signatur, die vier Teile und allenfalls binäre Puffer
+        let [_, signature, parts @ ..] = rest.as_slice() else {
+            return Err("Message without signature".to_string());
+        };
+        let [header, parent_header, metadata, content, ..] = parts else {
+            return Err("Incomplete message".to_string());
+        };
+        if !self.verify(signature, &parts[..4]) {
+            return Err("Invalid signature".to_string());
+        }
+        let parse = |part: &[u8]| {
+            serde_json::from_slice::<Value>(part).map_err(|e| format!("Invalid JSON ({})", e))
+        };
+        Ok(Message {
+            identities: frames,
+            header: parse(header)?,
+            parent_header: parse(parent_header)?,
+            metadata: parse(metadata)?,
+            content: parse(content)?,
+        })
+    }
+}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,442 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Jupyter-Kernel für UB++. Jupyter startet ihn mit `ubpp kernel <verbindungsdatei>`, registriert
+//! wird er mit `ubpp kernel --install`. Alle Zellen teilen sich einen [`Scope`], `gib us` und
+//! `e frog` laufen über die Zelle.
+
+pub mod message;
+pub mod zmtp;
+
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    lint::lint,
+    step1::{Atomic, RuntimeError, Token},
+    step2::has_strict_pragma,
+    step3::{eval_tokens, Console, Scope},
+};
+use message::{Message, Session, PROTOCOL_VERSION};
+use zmtp::{Frames, Publisher, Router};
+
+/// Inhalt der Verbindungsdatei, die Jupyter beim Start übergibt
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub ip: String,
+    pub key: String,
+    pub shell_port: u16,
+    pub iopub_port: u16,
+    pub stdin_port: u16,
+    pub control_port: u16,
+    pub hb_port: u16,
+}
+
+impl ConnectionInfo {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid connection file ({})", e))?;
+        if value["transport"] != "tcp" {
+            return Err(format!("Unsupported transport {}", value["transport"]));
+        }
+        let key = value["key"].as_str().unwrap_or_default().to_string();
+        if !key.is_empty() && value["signature_scheme"] != "hmac-sha256" {
+            return Err(format!(
+                "Unsupported signature scheme {}",
+                value["signature_scheme"]
+            ));
+        }
+        let port = |name: &str| {
+            value[name]
+                .as_u64()
+                .and_then(|port| u16::try_from(port).ok())
+                .ok_or_else(|| format!("Missing `{}` in connection file", name))
+        };
+        Ok(ConnectionInfo {
+            ip: value["ip"].as_str().unwrap_or("127.0.0.1").to_string(),
+            key,
+            shell_port: port("shell_port")?,
+            iopub_port: port("iopub_port")?,
+            stdin_port: port("stdin_port")?,
+            control_port: port("control_port")?,
+            hb_port: port("hb_port")?,
+        })
+    }
+
+    pub fn address(&self, port: u16) -> String {
+        format!("{}:{}", self.ip, port)
+    }
+}
+
+/// Sockets, die der Kernel und die Konsole der laufenden Zelle brauchen
+struct Channels {
+    session: Session,
+    iopub: Publisher,
+    stdin: Router,
+    input_replies: Receiver<Frames>,
+}
+
+impl Channels {
+    fn publish(&self, msg_type: &str, content: Value, parent: Option<&Message>) {
+        let mut message = self.session.message(msg_type, content, parent);
+        message.identities = vec![format!("kernel.{}.{}", self.session.id, msg_type).into_bytes()];
+        self.iopub.send(&self.session.encode(&message));
+    }
+}
+
+/// Leitet `gib us` und `e frog` der Zelle an Jupyter weiter
+struct CellConsole {
+    channels: Rc<Channels>,
+    request: Message,
+}
+
+impl Console for CellConsole {
+    fn print(&mut self, line: &str) {
+        self.channels.publish(
+            "stream",
+            json!({ "name": "stdout", "text": format!("{}\n", line) }),
+            Some(&self.request),
+        );
+    }
+
+    fn input(&mut self, prompt: &str) -> std::io::Result<String> {
+        let not_connected = |message: &str| {
+            std::io::Error::new(std::io::ErrorKind::NotConnected, message.to_string())
+        };
+        if self.request.content["allow_stdin"] == false {
+            return Err(not_connected("The frontend does not support input"));
+        }
+        let channels = &self.channels;
+        let request = channels.session.message(
+            "input_request",
+            json!({ "prompt": prompt, "password": false }),
+            Some(&self.request),
+        );
+        channels.stdin.send(&channels.session.encode(&request))?;
+        for frames in channels.input_replies.iter() {
+            match channels.session.decode(frames) {
+                Ok(reply) if reply.msg_type() == "input_reply" => {
+                    let value = reply.content["value"].as_str().unwrap_or_default();
+                    return Ok(format!("{}\n", value));
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[WARNING] {}", e),
+            }
+        }
+        Err(not_connected("The stdin channel is closed"))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Channel {
+    Shell,
+    Control,
+}
+
+struct Kernel<P> {
+    channels: Rc<Channels>,
+    shell: Router,
+    control: Router,
+    scope: Scope,
+    parse: P,
+    execution_count: u64,
+}
+
+/// Startet den Kernel und beantwortet Anfragen, bis Jupyter ihn beendet
+pub fn run(
+    connection: &ConnectionInfo,
+    scope: Scope,
+    parse: impl Fn(&str) -> Result<Vec<Token>, String>,
+) -> Result<(), String> {
+    let bind_error = |port: u16| {
+        let address = connection.address(port);
+        move |e: std::io::Error| format!("Cannot bind `{}` ({})", address, e)
+    };
+    let (sender, requests) = mpsc::channel();
+    let shell_sender = sender.clone();
+    let shell = Router::bind(connection.address(connection.shell_port), move |frames| {
+        let _ = shell_sender.send((Channel::Shell, frames));
+    })
+    .map_err(bind_error(connection.shell_port))?;
+    let control = Router::bind(connection.address(connection.control_port), move |frames| {
+        let _ = sender.send((Channel::Control, frames));
+    })
+    .map_err(bind_error(connection.control_port))?;
+    let (input_sender, input_replies) = mpsc::channel();
+    let stdin = Router::bind(connection.address(connection.stdin_port), move |frames| {
+        let _ = input_sender.send(frames);
+    })
+    .map_err(bind_error(connection.stdin_port))?;
+    let iopub = Publisher::bind(connection.address(connection.iopub_port))
+        .map_err(bind_error(connection.iopub_port))?;
+    zmtp::echo(connection.address(connection.hb_port)).map_err(bind_error(connection.hb_port))?;
+
+    let mut kernel = Kernel {
+        channels: Rc::new(Channels {
+            session: Session::new(&connection.key),
+            iopub,
+            stdin,
+            input_replies,
+        }),
+        shell,
+        control,
+        scope,
+        parse,
+        execution_count: 0,
+    };
+    kernel
+        .channels
+        .publish("status", json!({ "execution_state": "starting" }), None);
+    for (channel, frames) in requests {
+        let request = match kernel.channels.session.decode(frames) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("[WARNING] {}", e);
+                continue;
+            }
+        };
+        let status = |state: &str| json!({ "execution_state": state });
+        kernel
+            .channels
+            .publish("status", status("busy"), Some(&request));
+        let keep_running = kernel.handle(channel, &request);
+        kernel
+            .channels
+            .publish("status", status("idle"), Some(&request));
+        if !keep_running {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl<P: Fn(&str) -> Result<Vec<Token>, String>> Kernel<P> {
+    /// Beantwortet eine Anfrage und gibt zurück, ob der Kernel weiterlaufen soll
+    fn handle(&mut self, channel: Channel, request: &Message) -> bool {
+        let content = &request.content;
+        let (reply, content) = match request.msg_type() {
+            "kernel_info_request" => ("kernel_info_reply", kernel_info()),
+            "execute_request" => ("execute_reply", self.execute(request)),
+            "is_complete_request" => (
+                "is_complete_reply",
+                self.is_complete(content["code"].as_str().unwrap_or_default()),
+            ),
+            "complete_request" => (
+                "complete_reply",
+                self.complete(
+                    content["code"].as_str().unwrap_or_default(),
+                    content["cursor_pos"].as_u64().unwrap_or_default() as usize,
+                ),
+            ),
+            "inspect_request" => (
+                "inspect_reply",
+                json!({ "status": "ok", "found": false, "data": {}, "metadata": {} }),
+            ),
+            "history_request" => ("history_reply", json!({ "status": "ok", "history": [] })),
+            "comm_info_request" => ("comm_info_reply", json!({ "status": "ok", "comms": {} })),
+            // Eine laufende Zelle kann nicht unterbrochen werden, sie ist hier schon fertig
+            "interrupt_request" => ("interrupt_reply", json!({ "status": "ok" })),
+            "shutdown_request" => (
+                "shutdown_reply",
+                json!({ "status": "ok", "restart": content["restart"] == true }),
+            ),
+            other => {
+                eprintln!("[WARNING] Unsupported message type `{}`", other);
+                return true;
+            }
+        };
+        let socket = match channel {
+            Channel::Shell => &self.shell,
+            Channel::Control => &self.control,
+        };
+        let session = &self.channels.session;
+        let _ = socket.send(&session.encode(&session.message(reply, content, Some(request))));
+        request.msg_type() != "shutdown_request"
+    }
+
+    fn execute(&mut self, request: &Message) -> Value {
+        let code = request.content["code"].as_str().unwrap_or_default();
+        let silent = request.content["silent"] == true;
+        if !silent && request.content["store_history"] != false {
+            self.execution_count += 1;
+        }
+        let execution_count = self.execution_count;
+        self.channels.publish(
+            "execute_input",
+            json!({ "code": code, "execution_count": execution_count }),
+            Some(request),
+        );
+
+        let result = (self.parse)(code).and_then(|tokens| {
+            for warning in lint(&tokens) {
+                self.channels.publish(
+                    "stream",
+                    json!({ "name": "stderr", "text": format!("[WARNING] {}\n", warning) }),
+                    Some(request),
+                );
+            }
+            self.scope.console = Some(Box::new(CellConsole {
+                channels: self.channels.clone(),
+                request: request.clone(),
+            }));
+            // Ab einer Zelle mit `/* strikt */` gilt der strikte Modus für alle weiteren
+            if has_strict_pragma(code) {
+                self.scope.strict = true;
+            }
+            let result = eval_tokens(tokens, &mut self.scope);
+            self.scope.console = None;
+            result.map_err(|e: RuntimeError| e.to_string())
+        });
+        match result {
+            Ok(value) => {
+                if !silent && !matches!(value, Atomic::Null | Atomic::Interrupt) {
+                    self.channels.publish(
+                        "execute_result",
+                        json!({
+                            "execution_count": execution_count,
+                            "data": { "text/plain": value.to_string() },
+                            "metadata": {},
+                        }),
+                        Some(request),
+                    );
+                }
+                json!({
+                    "status": "ok",
+                    "execution_count": execution_count,
+                    "user_expressions": {},
+                    "payload": [],
+                })
+            }
+            Err(error) => {
+                let error = traceback(&error);
+                if !silent {
+                    self.channels.publish("error", error.clone(), Some(request));
+                }
+                let mut reply = error;
+                reply["status"] = json!("error");
+                reply["execution_count"] = json!(execution_count);
+                reply
+            }
+        }
+    }
+
+    /// Unvollständig ist eine Zelle mit offenen Klammern, Jupyter wartet dann auf weitere Zeilen
+    fn is_complete(&self, code: &str) -> Value {
+        if (self.parse)(code).is_ok() {
+            json!({ "status": "complete" })
+        } else if has_open_brackets(code) {
+            json!({ "status": "incomplete", "indent": "    " })
+        } else {
+            json!({ "status": "invalid" })
+        }
+    }
+
+    /// Vervollständigt die Namen der definierten Variablen
+    fn complete(&self, code: &str, cursor_pos: usize) -> Value {
+        let before = code.chars().take(cursor_pos).collect::<Vec<_>>();
+        let start = before
+            .iter()
+            .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+            .map_or(0, |i| i + 1);
+        let prefix = before[start..].iter().collect::<String>();
+        let mut matches = self
+            .scope
+            .variable_names()
+            .filter(|name| !prefix.is_empty() && name.starts_with(&prefix))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        matches.sort();
+        json!({
+            "status": "ok",
+            "matches": matches,
+            "cursor_start": start,
+            "cursor_end": before.len(),
+            "metadata": {},
+        })
+    }
+}
+
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "ubpp",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "ubpp",
+            "version": env!("CARGO_PKG_VERSION"),
+            "mimetype": "text/x-ubpp",
+            "file_extension": ".ubpp",
+        },
+        "banner": "UB++",
+        "help_links": [],
+    })
+}
+
+/// Fehler wie `no match: ...` werden mit roter Art und der Meldung Zeile für Zeile angezeigt
+fn traceback(error: &str) -> Value {
+    let (kind, message) = error.split_once(": ").unwrap_or(("error", error));
+    let mut traceback = vec![format!("\u{1b}[0;31merror: {}\u{1b}[0m", kind)];
+    traceback.extend(message.lines().map(str::to_string));
+    json!({ "ename": kind, "evalue": message, "traceback": traceback })
+}
+
+fn has_open_brackets(code: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in code.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' | '(' if !in_string => depth += 1,
+            '}' | ')' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+/// Verzeichnis, in dem Jupyter nach Kernels sucht
+fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("JUPYTER_DATA_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        return std::env::var_os("APPDATA").map(|dir| Path::new(&dir).join("jupyter"));
+    }
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library/Jupyter"))
+    } else {
+        Some(home.join(".local/share/jupyter"))
+    }
+}
+
+/// Registriert `executable` als Kernel für den aktuellen Benutzer und gibt die `kernel.json`
+/// zurück
+pub fn install(executable: &Path) -> Result<PathBuf, String> {
+    let dir = data_dir()
+        .ok_or("Cannot find the Jupyter data directory")?
+        .join("kernels/ubpp");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Cannot create `{}` ({})", dir.display(), e))?;
+    let spec = json!({
+        "argv": [executable, "kernel", "{connection_file}"],
+        "display_name": "UB++",
+        "language": "ubpp",
+    });
+    let path = dir.join("kernel.json");
+    std::fs::write(&path, format!("{:#}\n", spec))
+        .map_err(|e| format!("Cannot write `{}` ({})", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,348 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Minimale Umsetzung von ZMTP 3.0 (<https://rfc.zeromq.org/spec/23/>) über TCP, gerade genug
+//! für die Sockets eines Jupyter-Kernels und seiner Clients. Es gibt nur den `NULL`
+//! Mechanismus, d.h. keine Verschlüsselung, und jede Verbindung bekommt eigene Threads.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Eine Nachricht aus mehreren Frames
+pub type Frames = Vec<Vec<u8>>;
+
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+
+/// Grösster Frame, den eine Gegenstelle schicken darf. Grössere werden abgelehnt, bevor Speicher
+/// dafür reserviert wird. Code und Ausgaben einer Zelle sind weit kleiner.
+pub const MAX_FRAME_SIZE: u64 = 8 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketType {
+    Router,
+    Dealer,
+    Pub,
+    Sub,
+    Rep,
+    Req,
+}
+
+impl SocketType {
+    fn name(self) -> &'static str {
+        match self {
+            SocketType::Router => "ROUTER",
+            SocketType::Dealer => "DEALER",
+            SocketType::Pub => "PUB",
+            SocketType::Sub => "SUB",
+            SocketType::Rep => "REP",
+            SocketType::Req => "REQ",
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Eine Verbindung nach dem Handshake
+#[derive(Debug)]
+pub struct Connection {
+    stream: TcpStream,
+    /// Identität der Gegenstelle, leer wenn sie keine angegeben hat
+    pub peer_identity: Vec<u8>,
+}
+
+impl Connection {
+    /// Verbindet sich als Client, z.B. als `DEALER` mit einem `ROUTER`
+    pub fn connect(
+        address: impl ToSocketAddrs,
+        socket_type: SocketType,
+        identity: &[u8],
+    ) -> io::Result<Self> {
+        Self::handshake(TcpStream::connect(address)?, socket_type, identity, false)
+    }
+
+    fn accept(stream: TcpStream, socket_type: SocketType) -> io::Result<Self> {
+        Self::handshake(stream, socket_type, &[], true)
+    }
+
+    fn handshake(
+        mut stream: TcpStream,
+        socket_type: SocketType,
+        identity: &[u8],
+        as_server: bool,
+    ) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let mut greeting = [0u8; 64];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        greeting[32] = as_server as u8;
+        stream.write_all(&greeting)?;
+        let mut peer = [0u8; 64];
+        stream.read_exact(&mut peer)?;
+        if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 || &peer[12..16] != b"NULL" {
+            return Err(invalid_data("Unsupported ZMTP greeting"));
+        }
+
+        let mut ready = b"\x05READY".to_vec();
+        push_property(&mut ready, "Socket-Type", socket_type.name().as_bytes());
+        if matches!(
+            socket_type,
+            SocketType::Router | SocketType::Dealer | SocketType::Req
+        ) {
+            push_property(&mut ready, "Identity", identity);
+        }
+        stream.write_all(&encode_frame(COMMAND, &ready))?;
+        let (flags, command) = read_frame(&mut stream)?;
+        let properties = command
+            .strip_prefix(b"\x05READY")
+            .filter(|_| flags & COMMAND != 0)
+            .ok_or_else(|| invalid_data("Expected a READY command"))?;
+        let peer_identity = parse_properties(properties)?
+            .remove("Identity")
+            .unwrap_or_default();
+        Ok(Connection {
+            stream,
+            peer_identity,
+        })
+    }
+
+    pub fn send(&mut self, frames: &[Vec<u8>]) -> io::Result<()> {
+        let mut message = vec![];
+        for (i, frame) in frames.iter().enumerate() {
+            let flags = if i + 1 < frames.len() { MORE } else { 0 };
+            message.extend(encode_frame(flags, frame));
+        }
+        self.stream.write_all(&message)
+    }
+
+    /// Wartet auf die nächste Nachricht. Befehle wie `SUBSCRIBE` werden übersprungen.
+    pub fn recv(&mut self) -> io::Result<Frames> {
+        let mut frames = vec![];
+        loop {
+            let (flags, frame) = read_frame(&mut self.stream)?;
+            if flags & COMMAND != 0 {
+                continue;
+            }
+            frames.push(frame);
+            if flags & MORE == 0 {
+                return Ok(frames);
+            }
+        }
+    }
+
+    /// Abonniert bei einem `PUB` alle Nachrichten, die mit `topic` beginnen
+    pub fn subscribe(&mut self, topic: &[u8]) -> io::Result<()> {
+        let mut message = vec![1];
+        message.extend_from_slice(topic);
+        self.send(&[message])
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(Connection {
+            stream: self.stream.try_clone()?,
+            peer_identity: self.peer_identity.clone(),
+        })
+    }
+}
+
+fn push_property(command: &mut Vec<u8>, name: &str, value: &[u8]) {
+    command.push(name.len() as u8);
+    command.extend_from_slice(name.as_bytes());
+    command.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    command.extend_from_slice(value);
+}
+
+fn parse_properties(mut data: &[u8]) -> io::Result<HashMap<String, Vec<u8>>> {
+    let mut properties = HashMap::new();
+    while let Some((&name_length, rest)) = data.split_first() {
+        let name_length = name_length as usize;
+        if rest.len() < name_length + 4 {
+            return Err(invalid_data("Truncated property"));
+        }
+        let (name, rest) = rest.split_at(name_length);
+        let (value_length, rest) = rest.split_at(4);
+        let value_length = u32::from_be_bytes([
+            value_length[0],
+            value_length[1],
+            value_length[2],
+            value_length[3],
+        ]) as usize;
+        if rest.len() < value_length {
+            return Err(invalid_data("Truncated property"));
+        }
+        let (value, rest) = rest.split_at(value_length);
+        properties.insert(String::from_utf8_lossy(name).to_string(), value.to_vec());
+        data = rest;
+    }
+    Ok(properties)
+}
+
+fn encode_frame(flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = vec![];
+    if body.len() > u8::MAX as usize {
+        frame.push(flags | LONG);
+        frame.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    } else {
+        frame.extend_from_slice(&[flags, body.len() as u8]);
+    }
+    frame.extend_from_slice(body);
+    frame
+}
+
+fn read_frame(stream: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut flags = [0u8; 1];
+    stream.read_exact(&mut flags)?;
+    let flags = flags[0];
+    let size = if flags & LONG != 0 {
+        let mut size = [0u8; 8];
+        stream.read_exact(&mut size)?;
+        u64::from_be_bytes(size)
+    } else {
+        let mut size = [0u8; 1];
+        stream.read_exact(&mut size)?;
+        size[0] as u64
+    };
+    if size > MAX_FRAME_SIZE {
+        return Err(invalid_data("Frame too large"));
+    }
+    let mut body = vec![0u8; size as usize];
+    stream.read_exact(&mut body)?;
+    Ok((flags, body))
+}
+
+/// Nimmt im Hintergrund Verbindungen an und führt `serve` für jede in einem eigenen Thread aus
+fn listen(
+    address: impl ToSocketAddrs,
+    socket_type: SocketType,
+    serve: impl Fn(Connection) -> io::Result<()> + Send + Sync + 'static,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let serve = Arc::new(serve);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let serve = serve.clone();
+            thread::spawn(move || {
+                let _ = Connection::accept(stream, socket_type).and_then(|c| serve(c));
+            });
+        }
+    });
+    Ok(())
+}
+
+/// `ROUTER`: Empfangene Nachrichten beginnen mit der Identität des Absenders, gesendete gehen an
+/// die Verbindung mit der Identität im ersten Frame
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    peers: Arc<Mutex<HashMap<Vec<u8>, Connection>>>,
+}
+
+impl Router {
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        on_message: impl Fn(Frames) + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let router = Router::default();
+        let peers = router.peers.clone();
+        // Gegenstellen ohne eigene Identität bekommen eine fortlaufende Nummer
+        let next_identity = AtomicU32::new(1);
+        listen(address, SocketType::Router, move |mut connection| {
+            let mut identity = connection.peer_identity.clone();
+            if identity.is_empty() {
+                identity = vec![0];
+                identity.extend(next_identity.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+            }
+            peers
+                .lock()
+                .unwrap()
+                .insert(identity.clone(), connection.try_clone()?);
+            let result = loop {
+                match connection.recv() {
+                    Ok(frames) => {
+                        let mut message = vec![identity.clone()];
+                        message.extend(frames);
+                        on_message(message);
+                    }
+                    Err(e) => break Err(e),
+                }
+            };
+            peers.lock().unwrap().remove(&identity);
+            result
+        })?;
+        Ok(router)
+    }
+
+    /// Der erste Frame ist die Identität des Empfängers. Ist er nicht verbunden, geht die
+    /// Nachricht verloren.
+    pub fn send(&self, frames: &[Vec<u8>]) -> io::Result<()> {
+        let Some((identity, frames)) = frames.split_first() else {
+            return Ok(());
+        };
+        match self.peers.lock().unwrap().get_mut(identity) {
+            Some(connection) => connection.send(frames),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `PUB`: Sendet jede Nachricht an alle Abonnenten. Abonnemente werden ignoriert, die Abonnenten
+/// filtern selbst.
+#[derive(Debug, Clone, Default)]
+pub struct Publisher {
+    subscribers: Arc<Mutex<HashMap<u32, Connection>>>,
+}
+
+impl Publisher {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let publisher = Publisher::default();
+        let subscribers = publisher.subscribers.clone();
+        let next_id = AtomicU32::new(0);
+        listen(address, SocketType::Pub, move |mut connection| {
+            let id = next_id.fetch_add(1, Ordering::Relaxed);
+            subscribers
+                .lock()
+                .unwrap()
+                .insert(id, connection.try_clone()?);
+            // Liest die Abonnemente, bis die Verbindung geschlossen wird
+            while connection.recv().is_ok() {}
+            subscribers.lock().unwrap().remove(&id);
+            Ok(())
+        })?;
+        Ok(publisher)
+    }
+
+    pub fn send(&self, frames: &[Vec<u8>]) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|_, connection| connection.send(frames).is_ok());
+    }
+}
+
+/// `REP` für den Heartbeat: Jede Nachricht wird unverändert zurückgeschickt
+pub fn echo(address: impl ToSocketAddrs) -> io::Result<()> {
+    listen(address, SocketType::Rep, |mut connection| loop {
+        let frames = connection.recv()?;
+        connection.send(&frames)?;
+    })
+}
@@ -6,11 +6,18 @@
 
 use pest_derive::Parser;
 
+pub mod builtins;
+pub mod dialect;
+pub mod kernel;
+pub mod lint;
+pub mod resolve;
 pub mod step1;
 pub mod step2;
 pub mod step3;
 pub mod step4;
+pub mod testing;
+pub mod textmate;
 
 #[derive(Parser)]
-#[grammar = "/Users/patrickamrein/Documents/Ubique/git/introduction-to-rust/ubpp.pest"]
+#[grammar = "../../ubpp.pest"]
 pub struct UBPP;
\ No newline at end of file
@@ -0,0 +1,180 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Statische Checks über den AST, die das Programm nicht abbrechen, aber auf
+//! wahrscheinliche Fehler hinweisen.
+
+use crate::step1::{
+    grow_stack, BinaryOp, Cast, Comparison, Expression, LogicOp, Pattern, Statement, Token, UnaryOp,
+};
+
+/// Gibt alle Warnungen für die Tokens zurück
+pub fn lint(tokens: &[Token]) -> Vec<String> {
+    let mut warnings = vec![];
+    lint_tokens(tokens, &mut warnings);
+    warnings
+}
+
+fn lint_tokens(tokens: &[Token], warnings: &mut Vec<String>) {
+    grow_stack(|| {
+        for token in tokens {
+            match token {
+                Token::Expression(e) => lint_expression(e, warnings),
+                Token::Statement(stmt) => lint_statement(stmt, warnings),
+                Token::Break | Token::Return => {}
+            }
+        }
+    })
+}
+
+fn lint_statement(stmt: &Statement, warnings: &mut Vec<String>) {
+    match stmt {
+        Statement::VariableAssignment(assignment) => lint_expression(&assignment.value, warnings),
+        Statement::Conditional(conditional) => {
+            lint_expression(&conditional.condition, warnings);
+            lint_tokens(&conditional.body, warnings);
+            for branch in &conditional.else_if {
+                lint_expression(&branch.condition, warnings);
+                lint_tokens(&branch.body, warnings);
+            }
+            if let Some(else_body) = &conditional.else_body {
+                lint_tokens(else_body, warnings);
+            }
+        }
+        Statement::Expression(e) | Statement::Print(e) | Statement::Throw(e) => {
+            lint_expression(e, warnings)
+        }
+        Statement::Loop(loop_statement) => {
+            lint_expression(&loop_statement.condition, warnings);
+            lint_tokens(&loop_statement.body, warnings);
+        }
+        Statement::Match(match_statement) => {
+            lint_expression(&match_statement.value, warnings);
+            lint_default_arm(
+                match_statement.arms.iter().map(|arm| arm.patterns.as_slice()),
+                warnings,
+            );
+            for arm in &match_statement.arms {
+                lint_tokens(&arm.body, warnings);
+            }
+        }
+        Statement::Import(_) | Statement::Struct(_) | Statement::Enum(_) => {}
+        Statement::FieldAssignment(assignment) => lint_expression(&assignment.value, warnings),
+        Statement::Test(test) => lint_tokens(&test.body, warnings),
+        Statement::Expect(expect) => lint_expression(&expect.condition, warnings),
+        Statement::WriteFile(write_file) => {
+            lint_expression(&write_file.content, warnings);
+            lint_expression(&write_file.path, warnings);
+        }
+        Statement::Try(try_statement) => {
+            lint_tokens(&try_statement.body, warnings);
+            lint_tokens(&try_statement.catch_body, warnings);
+        }
+    }
+}
+
+fn lint_expression(e: &Expression, warnings: &mut Vec<String>) {
+    grow_stack(|| match e {
+        Expression::Atomic(_) | Expression::Ident(_) => {}
+        Expression::LogicOp(logic_op) => match logic_op.as_ref() {
+            LogicOp::And(lhs, rhs) | LogicOp::Or(lhs, rhs) => {
+                lint_expression(lhs, warnings);
+                lint_expression(rhs, warnings);
+            }
+        },
+        Expression::UnaryOp(unary_op) => match unary_op.as_ref() {
+            UnaryOp::Not(operand) | UnaryOp::Negate(operand) => lint_expression(operand, warnings),
+        },
+        Expression::Comparison(comparison) => match comparison.as_ref() {
+            Comparison::Smaller(lhs, rhs)
+            | Comparison::SmallerEquals(lhs, rhs)
+            | Comparison::Equals(lhs, rhs)
+            | Comparison::NotEquals(lhs, rhs)
+            | Comparison::Greater(lhs, rhs)
+            | Comparison::GreaterEquals(lhs, rhs) => {
+                lint_expression(lhs, warnings);
+                lint_expression(rhs, warnings);
+            }
+        },
+        Expression::BinaryOp(binary_op) => match binary_op.as_ref() {
+            BinaryOp::Plus { left, right }
+            | BinaryOp::Minus { left, right }
+            | BinaryOp::Mul { left, right }
+            | BinaryOp::Div { left, right }
+            | BinaryOp::Mod { left, right }
+            | BinaryOp::Pow { left, right } => {
+                lint_expression(left, warnings);
+                lint_expression(right, warnings);
+            }
+            BinaryOp::None => {}
+        },
+        Expression::Conditional(conditional) => {
+            lint_expression(&conditional.condition, warnings);
+            lint_tokens(&conditional.body, warnings);
+            lint_expression(&conditional.body_expression, warnings);
+            for branch in &conditional.else_if {
+                lint_expression(&branch.condition, warnings);
+                lint_tokens(&branch.body, warnings);
+                lint_expression(&branch.body_expression, warnings);
+            }
+            lint_tokens(&conditional.else_body, warnings);
+            lint_expression(&conditional.else_body_expression, warnings);
+        }
+        Expression::Match(match_expression) => {
+            lint_expression(&match_expression.value, warnings);
+            lint_default_arm(
+                match_expression.arms.iter().map(|arm| arm.patterns.as_slice()),
+                warnings,
+            );
+            for arm in &match_expression.arms {
+                lint_tokens(&arm.body, warnings);
+                lint_expression(&arm.body_expression, warnings);
+            }
+        }
+        Expression::Input(e) | Expression::ReadFile(e) => lint_expression(e, warnings),
+        Expression::FieldAccess(field_access) => lint_expression(&field_access.value, warnings),
+        Expression::Random(range) => {
+            lint_expression(&range.from, warnings);
+            lint_expression(&range.to, warnings);
+        }
+        Expression::Call(call) => {
+            lint_expression(&call.function, warnings);
+            for argument in &call.arguments {
+                lint_expression(argument, warnings);
+            }
+        }
+        Expression::Function(definition) => {
+            lint_tokens(&definition.body, warnings);
+            if let Some(body_expression) = &definition.body_expression {
+                lint_expression(body_expression, warnings);
+            }
+        }
+        Expression::Cast(cast) => match cast.as_ref() {
+            Cast::String(e)
+            | Cast::Number(e)
+            | Cast::Integer(e)
+            | Cast::Float(e)
+            | Cast::Bool(e)
+            | Cast::Json(e)
+            | Cast::FromJson(e) => lint_expression(e, warnings),
+        },
+    })
+}
+
+/// Bei Varianten bricht `lueg ob` ohne passenden Arm mit einem Fehler ab, dort braucht es keinen
+/// `susch` Arm
+fn lint_default_arm<'a>(
+    arms: impl Iterator<Item = &'a [Pattern]>,
+    warnings: &mut Vec<String>,
+) {
+    let mut has_default = false;
+    for pattern in arms.flatten() {
+        has_default |= matches!(pattern, Pattern::Default | Pattern::Variant { .. });
+    }
+    if !has_default {
+        warnings.push("`lueg ob` ohni `susch` Arm".to_string());
+    }
+}
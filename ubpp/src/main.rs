@@ -4,25 +4,250 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-extern crate pest;
+use std::path::{Path, PathBuf};
 
-use std::collections::HashMap;
+use chrono::NaiveDateTime;
+use ubpplib::{
+    builtins::{parse_date, Permissions},
+    dialect::Dialect,
+    kernel::{self, ConnectionInfo},
+    lint::lint,
+    step1::Token,
+    step2::{has_strict_pragma, MAX_NESTING},
+    step3::{eval_tokens, Scope},
+    testing::{find_files, run_tests},
+};
 
-use pest::Parser;
+/// Einstellungen aus der Kommandozeile, die für jeden Scope gelten
+struct Options {
+    dialect: Dialect,
+    permissions: Permissions,
+    now: Option<NaiveDateTime>,
+    seed: Option<u64>,
+    strict: bool,
+    nesting_limit: Option<usize>,
+    depth_limit: Option<usize>,
+}
+
+impl Options {
+    /// `strict` gilt zusätzlich zu `--strikt`, z.B. für eine Datei mit `/* strikt */`
+    fn new_scope(&self, path: &Path, strict: bool) -> Scope {
+        let mut scope = Scope::new(path);
+        scope.permissions = self.permissions.clone();
+        scope.now = self.now;
+        scope.seed = self.seed;
+        scope.strict = self.strict || strict;
+        scope.nesting_limit = self.nesting_limit;
+        scope.depth_limit = self.depth_limit;
+        scope
+    }
 
-use ubpplib::{step2::parse_body, step3::eval_tokens, Rule};
+    /// Liest und parst eine Datei, ein Dialekt-Kommentar in der Datei hat Vorrang. Gibt auch
+    /// zurück, ob die Datei strikt ist. Die Fehler sind wie bei [`ubpplib::step1::RuntimeError`]
+    /// als `art: meldig` formatiert.
+    fn parse(&self, path: &Path) -> Result<(Vec<Token>, bool), String> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|e| format!("file: Cannot read `{}` ({})", path.display(), e))?;
+        Ok((self.parse_source(&input)?, has_strict_pragma(&input)))
+    }
+
+    fn parse_source(&self, input: &str) -> Result<Vec<Token>, String> {
+        let dialect = match Dialect::from_header(input) {
+            Some(header) => header.map_err(|e| format!("dialect: {}", e))?,
+            None => self.dialect,
+        };
+        dialect
+            .parse(input, self.nesting_limit.unwrap_or(MAX_NESTING))
+            .map_err(|e| format!("parse: {}", e))
+    }
+}
 
+/// Aufruf: `ubpp [optione] [datei]`, `ubpp test [optione] [dateie/verzeichnis...]` oder
+/// `ubpp kernel [optione] <verbindungsdatei>` für Jupyter (`ubpp kernel --install` registriert den
+/// Kernel) mit den Optionen
+/// - `--dialekt <name>`
+/// - `--allow-read=<pfad>`, `--allow-write=<pfad>`: ohne diese darf das Programm keine Dateien
+///   lesen oder schreiben
+/// - `--jetzt=<datum>`: fixe Zeit für `jetzt()`, z.B. `--jetzt="18.10.2026 14:30"`
+/// - `--seed=<zahl>`: Seed für `zuefallszahl`, `zuefallswahl` und `mische`
+/// - `--strikt`: keine impliziten Umwandlungen, wie mit `/* strikt */` in der Datei
+/// - `--max-verschachtelig=<zahl>`: so tief dürfen Blöcke und Ausdrücke beim Parsen verschachtelt
+///   sein, standardmässig 256
+/// - `--max-tiefi=<zahl>`: so tief darf die Auswertung werden, z.B. bei Rekursion, standardmässig
+///   5000
 fn main() {
-    let input = std::fs::read_to_string("./example.ubpp").unwrap();
-    let parse_result = ubpplib::UBPP::parse(Rule::file, &input)
-        .unwrap()
-        .next()
-        .unwrap()
-        .into_inner()
-        .next()
-        .unwrap();
-    let tokens = parse_body(parse_result);
-    let mut global_tokens = HashMap::new();
-    let result = eval_tokens(&tokens, &mut global_tokens).unwrap();
-    println!("{}", result);
+    let mut paths = vec![];
+    let mut mode = Mode::Run;
+    let mut install = false;
+    let mut options = Options {
+        dialect: Dialect::default(),
+        permissions: Permissions::default(),
+        now: None,
+        seed: None,
+        strict: false,
+        nesting_limit: None,
+        depth_limit: None,
+    };
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("test") => mode = Mode::Test,
+        Some("kernel") => mode = Mode::Kernel,
+        _ => {}
+    }
+    if mode != Mode::Run {
+        args.next();
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialekt" => {
+                let Some(name) = args.next() else {
+                    fail("dialect: --dialekt needs a value");
+                };
+                options.dialect = name
+                    .parse()
+                    .unwrap_or_else(|e| fail(format!("dialect: {}", e)));
+            }
+            _ if arg.starts_with("--allow-read=") => {
+                let allowed = &arg["--allow-read=".len()..];
+                options.permissions.allow_read(allowed).unwrap_or_else(|e| {
+                    fail(format!("permission: Cannot allow `{}` ({})", allowed, e))
+                });
+            }
+            _ if arg.starts_with("--allow-write=") => {
+                let allowed = &arg["--allow-write=".len()..];
+                options
+                    .permissions
+                    .allow_write(allowed)
+                    .unwrap_or_else(|e| {
+                        fail(format!("permission: Cannot allow `{}` ({})", allowed, e))
+                    });
+            }
+            _ if arg.starts_with("--jetzt=") => {
+                options.now =
+                    Some(parse_date(&arg["--jetzt=".len()..]).unwrap_or_else(|e| fail(e)));
+            }
+            _ if arg.starts_with("--seed=") => {
+                let seed = &arg["--seed=".len()..];
+                options.seed = Some(seed.parse().unwrap_or_else(|e| {
+                    fail(format!("argument: `{}` is not a valid seed ({})", seed, e))
+                }));
+            }
+            _ if arg.starts_with("--max-verschachtelig=") => {
+                options.nesting_limit = Some(limit(&arg["--max-verschachtelig=".len()..]));
+            }
+            _ if arg.starts_with("--max-tiefi=") => {
+                options.depth_limit = Some(limit(&arg["--max-tiefi=".len()..]));
+            }
+            "--strikt" => options.strict = true,
+            "--install" if mode == Mode::Kernel => install = true,
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    match mode {
+        Mode::Run => {}
+        Mode::Test => {
+            if paths.is_empty() {
+                paths.push(PathBuf::from("."));
+            }
+            if !test(&paths, &options) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Kernel if install => {
+            let executable = std::env::current_exe().unwrap_or_else(|e| fail(e));
+            let spec = kernel::install(&executable).unwrap_or_else(|e| fail(e));
+            println!("Kernel installed in {}", spec.display());
+            return;
+        }
+        Mode::Kernel => {
+            let Some(path) = paths.pop() else {
+                fail("kernel: Missing connection file");
+            };
+            if let Err(e) = run_kernel(&path, &options) {
+                fail(format!("kernel: {}", e));
+            }
+            return;
+        }
+    }
+
+    let path = paths
+        .pop()
+        .unwrap_or_else(|| PathBuf::from("./example.ubpp"));
+    let (tokens, strict) = options.parse(&path).unwrap_or_else(|e| fail(e));
+    for warning in lint(&tokens) {
+        eprintln!("[WARNING] {}", warning);
+    }
+    let mut global_scope = options.new_scope(&path, strict);
+    match eval_tokens(tokens, &mut global_scope) {
+        Ok(result) => println!("{}", result),
+        Err(e) => fail(e),
+    }
+}
+
+#[derive(PartialEq)]
+enum Mode {
+    Run,
+    Test,
+    Kernel,
+}
+
+/// Relative Pfade, z.B. für `bruuch`, gelten ab dem Verzeichnis, in dem Jupyter den Kernel
+/// startet, d.h. dem des Notebooks
+fn run_kernel(connection_file: &Path, options: &Options) -> Result<(), String> {
+    let connection = std::fs::read_to_string(connection_file)
+        .map_err(|e| format!("Cannot read `{}` ({})", connection_file.display(), e))?;
+    let connection = ConnectionInfo::from_json(&connection)?;
+    let scope = options.new_scope(Path::new("notebook.ubpp"), false);
+    kernel::run(&connection, scope, |source| options.parse_source(source))
+}
+
+/// Gibt den Fehler als `error: art: meldig` aus und beendet das Programm
+fn fail(error: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", error);
+    std::process::exit(1);
+}
+
+fn limit(value: &str) -> usize {
+    value.parse().unwrap_or_else(|e| {
+        fail(format!(
+            "argument: `{}` is not a valid limit ({})",
+            value, e
+        ))
+    })
+}
+
+/// Führt alle Tests aus und gibt zurück, ob alle erfolgreich waren
+fn test(paths: &[PathBuf], options: &Options) -> bool {
+    let mut passed = 0;
+    let mut failed = 0;
+    let files =
+        find_files(paths).unwrap_or_else(|e| fail(format!("file: Cannot list the tests ({})", e)));
+    for path in files {
+        let (tokens, strict) = match options.parse(&path) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{} ... FAILED\n{}", path.display(), e);
+                failed += 1;
+                continue;
+            }
+        };
+        for result in run_tests(&tokens, || options.new_scope(&path, strict)) {
+            let location = format!("{}:{}", path.display(), result.line);
+            match result.outcome {
+                Ok(()) => {
+                    println!("test {} \"{}\" ... ok", location, result.name);
+                    passed += 1;
+                }
+                Err(e) => {
+                    println!("test {} \"{}\" ... FAILED", location, result.name);
+                    println!("    {}", e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("\n{} passed, {} failed", passed, failed);
+    failed == 0
 }
@@ -0,0 +1,353 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Löst vor der Ausführung alle Variablen auf: Jeder Name bekommt einen Slot, d.h. einen Index
+//! in die Werte des [`Scope`], so dass die Auswertung keine Namen mehr nachschlagen muss. Namen,
+//! die im ganzen Programm nirgends definiert werden, sind schon vor dem Start ein Fehler.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    builtins,
+    step1::{
+        grow_stack, Atomic, BinaryOp, Cast, Comparison, ErrorKind, Expression, FunctionDefinition,
+        LogicOp, Pattern, RuntimeError, Statement, Token, UnaryOp, Variable,
+    },
+    step3::{module_name, Scope},
+};
+
+/// Die Namen eines Programms und ihre Slots. Ein Name hat überall denselben Slot, auch in
+/// Funktionen und importierten Modulen, so dass eine Funktion ihre eingefangenen Werte beim Aufruf
+/// direkt in deren Slots legen kann.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Names {
+    slots: HashMap<Rc<str>, usize>,
+    names: Vec<Rc<str>>,
+    /// Die eingebaute Funktion oder Konstante mit dem Namen, falls es eine gibt
+    builtins: Vec<Option<Atomic>>,
+}
+
+impl Names {
+    /// Gibt den Slot des Namens zurück, ein neuer Name bekommt den nächsten freien
+    pub(crate) fn intern(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.slots.get(name) {
+            return slot;
+        }
+        let name: Rc<str> = name.into();
+        let slot = self.names.len();
+        self.slots.insert(name.clone(), slot);
+        self.builtins.push(builtins::lookup(&name));
+        self.names.push(name);
+        slot
+    }
+
+    pub(crate) fn name(&self, slot: usize) -> &Rc<str> {
+        &self.names[slot]
+    }
+
+    pub(crate) fn builtin(&self, slot: usize) -> Option<&Atomic> {
+        self.builtins.get(slot)?.as_ref()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Übernimmt die Namen eines aufgelösten Programms, falls die bisherigen Namen dieselben Slots
+    /// haben wie dort, z.B. in einem frischen Scope. Gibt zurück, ob die Slots des Programms gelten.
+    pub(crate) fn adopt(&mut self, program: &Names) -> bool {
+        let common = self.len().min(program.len());
+        if self.names[..common] != program.names[..common] {
+            return false;
+        }
+        if self.len() < program.len() {
+            *self = program.clone();
+        }
+        true
+    }
+}
+
+/// Tokens, deren Variablen aufgelöst sind, zusammen mit den Namen der Slots. Wird mit
+/// [`crate::step3::eval_program`] ausgewertet, auch mehrmals.
+#[derive(Debug, Clone)]
+pub struct Program {
+    tokens: Vec<Token>,
+    names: Names,
+}
+
+impl Program {
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub(crate) fn names(&self) -> &Names {
+        &self.names
+    }
+}
+
+/// Vergibt die Slots für `tokens` und prüft, dass jede gelesene Variable irgendwo definiert wird,
+/// schon einen Wert im `scope` hat oder eine eingebaute Funktion ist. Ob die Definition zur
+/// Laufzeit auch vor dem Lesen ausgeführt wird, prüft erst die Auswertung.
+pub fn resolve(mut tokens: Vec<Token>, scope: &mut Scope) -> Result<Program, RuntimeError> {
+    resolve_tokens(&mut tokens, scope)?;
+    Ok(Program {
+        tokens,
+        names: scope.names.clone(),
+    })
+}
+
+/// Wie [`resolve`], aber an Ort und Stelle, z.B. für ein Modul
+pub(crate) fn resolve_tokens(tokens: &mut [Token], scope: &mut Scope) -> Result<(), RuntimeError> {
+    let mut resolver = Resolver {
+        names: &mut scope.names,
+        defined: HashSet::new(),
+        reads: vec![],
+    };
+    resolver.tokens(tokens);
+    let Resolver { defined, reads, .. } = resolver;
+    for slot in reads {
+        if !defined.contains(&slot)
+            && !scope.is_defined(slot)
+            && scope.names.builtin(slot).is_none()
+        {
+            return Err(RuntimeError::new(
+                ErrorKind::Undefined,
+                format!("`{}` not defined!", scope.names.name(slot)),
+            ));
+        }
+    }
+    Ok(())
+}
+
+struct Resolver<'a> {
+    names: &'a mut Names,
+    /// Slots, denen das Programm irgendwo einen Wert gibt
+    defined: HashSet<usize>,
+    /// Gelesene Slots in der Reihenfolge im Programm
+    reads: Vec<usize>,
+}
+
+impl Resolver<'_> {
+    /// Vergibt den Slot, alle Vorkommen eines Namens teilen sich danach denselben String
+    fn slot(&mut self, variable: &mut Variable) -> usize {
+        variable.slot = self.names.intern(&variable.name);
+        variable.name = self.names.name(variable.slot).clone();
+        variable.slot
+    }
+
+    fn read(&mut self, variable: &mut Variable) {
+        let slot = self.slot(variable);
+        self.reads.push(slot);
+    }
+
+    fn define(&mut self, variable: &mut Variable) {
+        let slot = self.slot(variable);
+        self.defined.insert(slot);
+    }
+
+    /// Die Slots, die eine Funktion seit `start` liest, ohne ihre Parameter und sich selbst. Danach
+    /// gelten nur noch diese als gelesen, so dass eine äussere Funktion die Parameter einer
+    /// inneren nicht einfängt.
+    fn captures(&mut self, start: usize, definition: &FunctionDefinition) -> Vec<usize> {
+        let mut captures = vec![];
+        for slot in self.reads.drain(start..) {
+            if !captures.contains(&slot)
+                && definition.own_slot != Some(slot)
+                && definition.parameters.iter().all(|p| p.slot != slot)
+            {
+                captures.push(slot);
+            }
+        }
+        self.reads.extend_from_slice(&captures);
+        captures
+    }
+
+    /// Für Strukturen, Varianten und Module, die ihren Slot erst bei der Ausführung nachschlagen
+    fn define_name(&mut self, name: &str) {
+        let slot = self.names.intern(name);
+        self.defined.insert(slot);
+    }
+
+    fn tokens(&mut self, tokens: &mut [Token]) {
+        grow_stack(|| {
+            for token in tokens {
+                match token {
+                    Token::Expression(e) => self.expression(e),
+                    Token::Statement(stmt) => self.statement(stmt),
+                    Token::Break | Token::Return => {}
+                }
+            }
+        })
+    }
+
+    fn statement(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::VariableAssignment(assignment) => {
+                self.define(&mut assignment.ident);
+                if let Expression::Function(definition) = &mut assignment.value {
+                    Rc::make_mut(definition).own_slot = Some(assignment.ident.slot);
+                }
+                self.expression(&mut assignment.value);
+            }
+            Statement::Conditional(conditional) => {
+                self.expression(&mut conditional.condition);
+                self.tokens(&mut conditional.body);
+                for branch in &mut conditional.else_if {
+                    self.expression(&mut branch.condition);
+                    self.tokens(&mut branch.body);
+                }
+                if let Some(else_body) = &mut conditional.else_body {
+                    self.tokens(else_body);
+                }
+            }
+            Statement::Expression(e) | Statement::Print(e) | Statement::Throw(e) => {
+                self.expression(e)
+            }
+            Statement::Loop(loop_statement) => {
+                self.expression(&mut loop_statement.condition);
+                self.tokens(&mut loop_statement.body);
+            }
+            Statement::Match(match_statement) => {
+                self.expression(&mut match_statement.value);
+                for arm in &mut match_statement.arms {
+                    self.patterns(&mut arm.patterns);
+                    self.tokens(&mut arm.body);
+                }
+            }
+            Statement::Try(try_statement) => {
+                self.tokens(&mut try_statement.body);
+                self.define(&mut try_statement.error_ident);
+                self.tokens(&mut try_statement.catch_body);
+            }
+            // Das Modul heisst wie die Datei, siehe `step3::eval_import`
+            Statement::Import(path) => self.define_name(&module_name(Path::new(path))),
+            Statement::Struct(definition) => self.define_name(&definition.name),
+            Statement::Enum(definition) => {
+                for variant in &definition.variants {
+                    self.define_name(&variant.name);
+                }
+            }
+            Statement::FieldAssignment(assignment) => {
+                self.expression(&mut assignment.value);
+                self.read(&mut assignment.ident);
+            }
+            Statement::Test(test) => self.tokens(&mut test.body),
+            Statement::Expect(expect) => self.expression(&mut expect.condition),
+            Statement::WriteFile(write_file) => {
+                self.expression(&mut write_file.content);
+                self.expression(&mut write_file.path);
+            }
+        }
+    }
+
+    fn patterns(&mut self, patterns: &mut [Pattern]) {
+        for pattern in patterns {
+            if let Pattern::Variant { name, bindings } = pattern {
+                self.read(name);
+                for binding in bindings {
+                    self.define(binding);
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, e: &mut Expression) {
+        grow_stack(|| match e {
+            Expression::Atomic(_) => {}
+            Expression::Ident(variable) => self.read(variable),
+            Expression::LogicOp(logic_op) => match logic_op.as_mut() {
+                LogicOp::And(lhs, rhs) | LogicOp::Or(lhs, rhs) => {
+                    self.expression(lhs);
+                    self.expression(rhs);
+                }
+            },
+            Expression::UnaryOp(unary_op) => match unary_op.as_mut() {
+                UnaryOp::Not(operand) | UnaryOp::Negate(operand) => self.expression(operand),
+            },
+            Expression::Comparison(comparison) => match comparison.as_mut() {
+                Comparison::Smaller(lhs, rhs)
+                | Comparison::SmallerEquals(lhs, rhs)
+                | Comparison::Equals(lhs, rhs)
+                | Comparison::NotEquals(lhs, rhs)
+                | Comparison::Greater(lhs, rhs)
+                | Comparison::GreaterEquals(lhs, rhs) => {
+                    self.expression(lhs);
+                    self.expression(rhs);
+                }
+            },
+            Expression::BinaryOp(binary_op) => match binary_op.as_mut() {
+                BinaryOp::Plus { left, right }
+                | BinaryOp::Minus { left, right }
+                | BinaryOp::Mul { left, right }
+                | BinaryOp::Div { left, right }
+                | BinaryOp::Mod { left, right }
+                | BinaryOp::Pow { left, right } => {
+                    self.expression(left);
+                    self.expression(right);
+                }
+                BinaryOp::None => {}
+            },
+            Expression::Conditional(conditional) => {
+                self.expression(&mut conditional.condition);
+                self.tokens(&mut conditional.body);
+                self.expression(&mut conditional.body_expression);
+                for branch in &mut conditional.else_if {
+                    self.expression(&mut branch.condition);
+                    self.tokens(&mut branch.body);
+                    self.expression(&mut branch.body_expression);
+                }
+                self.tokens(&mut conditional.else_body);
+                self.expression(&mut conditional.else_body_expression);
+            }
+            Expression::Match(match_expression) => {
+                self.expression(&mut match_expression.value);
+                for arm in &mut match_expression.arms {
+                    self.patterns(&mut arm.patterns);
+                    self.tokens(&mut arm.body);
+                    self.expression(&mut arm.body_expression);
+                }
+            }
+            Expression::Input(e) | Expression::ReadFile(e) => self.expression(e),
+            Expression::FieldAccess(field_access) => self.expression(&mut field_access.value),
+            Expression::Random(range) => {
+                self.expression(&mut range.from);
+                self.expression(&mut range.to);
+            }
+            Expression::Call(call) => {
+                self.expression(&mut call.function);
+                for argument in &mut call.arguments {
+                    self.expression(argument);
+                }
+            }
+            // Nur eine Kopie, wenn die Definition schon von einer Funktion geteilt wird
+            Expression::Function(definition) => {
+                let definition = Rc::make_mut(definition);
+                let start = self.reads.len();
+                for parameter in &mut definition.parameters {
+                    self.define(parameter);
+                }
+                self.tokens(&mut definition.body);
+                if let Some(body_expression) = &mut definition.body_expression {
+                    self.expression(body_expression);
+                }
+                definition.captures = self.captures(start, definition);
+            }
+            Expression::Cast(cast) => match cast.as_mut() {
+                Cast::String(e)
+                | Cast::Number(e)
+                | Cast::Integer(e)
+                | Cast::Float(e)
+                | Cast::Bool(e)
+                | Cast::Json(e)
+                | Cast::FromJson(e) => self.expression(e),
+            },
+        })
+    }
+}
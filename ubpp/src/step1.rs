@@ -4,15 +4,113 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+use chrono::NaiveDateTime;
+use num_bigint::BigInt;
+
+use crate::builtins::Builtin;
+
 #[derive(Debug, Clone)]
 pub enum Atomic {
     String(String),
     Number(f64),
+    /// Ganzzahl mit beliebiger Genauigkeit
+    Integer(BigInt),
     Bool(bool),
     Null,
+    Error(RuntimeError),
+    Module(Module),
+    /// Eingebaute Funktion, z.B. `wurzel`
+    Builtin(&'static Builtin),
+    /// Liste von Werten, z.B. das Resultat von `teile`
+    List(Rc<Vec<Atomic>>),
+    /// Zuordnung von Namen zu Werten, z.B. ein JSON Objekt
+    Map(Rc<BTreeMap<String, Atomic>>),
+    /// Datum mit Uhrzeit, z.B. von `datum("18.10.2026")`
+    Date(NaiveDateTime),
+    /// Anonyme Funktion, z.B. `funktion (x) { x mol 2 }`
+    Function(Rc<Closure>),
+    /// Eine Struktur, aufgerufen erstellt sie einen [`Atomic::Record`]: `Person("Anna", 12)`
+    Struct(Rc<Struct>),
+    Record(Rc<Record>),
+    /// Eine Variante mit Feldern, aufgerufen erstellt sie einen [`Atomic::VariantValue`]:
+    /// `Kreis(2)`
+    Variant(Rc<Variant>),
+    VariantValue(Rc<VariantValue>),
     Interrupt
 }
 
+/// Ein mit `bruuch` geladenes Modul. Die Variablen sind über `modul.name` erreichbar.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub variables: Rc<HashMap<String, Atomic>>,
+}
+
+/// Ein Fehler, der während der Ausführung auftritt und mit `probier`/`fang` abgefangen werden kann
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// Ist weniger Stack frei, wird ein neuer Abschnitt auf dem Heap angelegt
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_GROWTH: usize = 4 * 1024 * 1024;
+
+/// Für rekursive Funktionen über den Syntaxbaum: Führt `f` nötigenfalls auf einem neuen
+/// Stack-Abschnitt aus, so dass auch tief verschachtelte Programme den Stack nicht sprengen
+pub(crate) fn grow_stack<T>(f: impl FnOnce() -> T) -> T {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, f)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Ein Wert hat nicht den erwarteten Typ, z.B. `"hallo" als Zahl`
+    Type,
+    /// Eine Variable wurde nicht definiert
+    Undefined,
+    /// Eine Variable wurde doppelt definiert
+    AlreadyDefined,
+    /// Lesen von der Eingabe ist fehlgeschlagen
+    Input,
+    /// Kein Arm eines `lueg ob` passt
+    NoMatch,
+    /// Eine Rechnung ist nicht definiert, z.B. `5 rest 0`
+    Arithmetic,
+    /// Mit `wirf` selbst ausgelöst
+    Thrown,
+    /// Ein Modul konnte nicht geladen werden
+    Import,
+    /// Eine Funktion wurde mit falschen Argumenten aufgerufen
+    Argument,
+    /// Ein Index liegt ausserhalb einer Zeichenkette oder Liste
+    Index,
+    /// Der Zugriff auf eine Datei ist nicht freigegeben
+    Permission,
+    /// Eine Datei konnte nicht gelesen oder geschrieben werden
+    File,
+    /// Ungültiges JSON oder ein Wert, der sich nicht als JSON darstellen lässt
+    Json,
+    /// Ein `erwart` ist nicht erfüllt
+    Expectation,
+    /// Das Programm hat mehr Schritte gebraucht als erlaubt, siehe [`crate::step3::Scope::step_limit`]
+    Limit,
+}
+
 #[derive(Debug, Clone)]
 pub enum LogicOp {
     And(Expression, Expression),
@@ -20,11 +118,20 @@ pub enum LogicOp {
 }
 
 
+#[derive(Debug, Clone)]
+pub enum UnaryOp {
+    /// `nöd x` oder `!x`
+    Not(Expression),
+    /// `-x`
+    Negate(Expression),
+}
+
 #[derive(Debug, Clone)]
 pub enum Comparison {
     Smaller(Expression, Expression),
     SmallerEquals(Expression, Expression),
     Equals(Expression, Expression),
+    NotEquals(Expression, Expression),
     Greater(Expression, Expression),
     GreaterEquals(Expression, Expression),
 }
@@ -46,28 +153,144 @@ pub struct ConditionalExpression {
     pub condition: Box<Expression>,
     pub body: Vec<Token>,
     pub body_expression: Box<Expression>,
+    pub else_if: Vec<ConditionalExpressionBranch>,
     pub else_body: Vec<Token>,
     pub else_body_expression: Box<Expression>,
 }
 
+/// Ein `suscht falls` Zweig einer [`ConditionalExpression`]
+#[derive(Debug, Clone)]
+pub struct ConditionalExpressionBranch {
+    pub condition: Box<Expression>,
+    pub body: Vec<Token>,
+    pub body_expression: Box<Expression>,
+}
+
+
+/// Ein Muster in einem `lueg ob` Arm
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `isch 1`, `isch "a"`
+    Literal(Atomic),
+    /// `isch 1 bis 10`, die Grenzen sind inklusiv
+    Range(Atomic, Atomic),
+    /// `isch Kreis(r)`, passt auf eine Variante und bindet ihre Felder an die Namen. Ohne
+    /// Klammern werden die Felder ignoriert.
+    Variant {
+        name: Variable,
+        bindings: Vec<Variable>,
+    },
+    /// `susch`, passt immer
+    Default,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchExpression {
+    pub value: Box<Expression>,
+    pub arms: Vec<MatchExpressionArm>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchExpressionArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Vec<Token>,
+    pub body_expression: Box<Expression>,
+}
+
+/// Zugriff auf ein Feld eines Wertes, z.B. `fehler.meldig`
+#[derive(Debug, Clone)]
+pub struct FieldAccess {
+    pub value: Expression,
+    pub field: String,
+}
+
+/// Funktionsaufruf, z.B. `wurzel(16)`
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub function: Expression,
+    pub arguments: Vec<Expression>,
+}
+
+/// `funktion (x, y) { ... }`. Das Resultat ist der Ausdruck am Ende des Körpers, fehlt er, ist
+/// es `null`.
+#[derive(Debug, Clone)]
+pub struct FunctionDefinition {
+    pub parameters: Vec<Variable>,
+    pub body: Vec<Token>,
+    pub body_expression: Option<Expression>,
+    /// Slots der Variablen von ausserhalb, die der Körper liest. Vergibt [`crate::resolve`].
+    pub captures: Vec<usize>,
+    /// Slot der Variable bei `loss f = funktion ...`, unter dem sich die Funktion selbst sieht
+    pub own_slot: Option<usize>,
+}
+
+/// Eine Funktion zusammen mit den Werten, die ihre [`FunctionDefinition::captures`] bei der
+/// Definition hatten. Variablen ohne Wert fehlen.
+#[derive(Debug)]
+pub struct Closure {
+    pub definition: Rc<FunctionDefinition>,
+    pub captured: Vec<(usize, Atomic)>,
+}
+
+/// `zuefallszahl zwüsche 1 und 100`
+#[derive(Debug, Clone)]
+pub struct RandomRange {
+    pub from: Expression,
+    pub to: Expression,
+}
 
 #[derive(Debug, Clone)]
 pub enum Cast {
     String(Expression),
-    Int(Expression),
+    /// `als Zahl`, ergibt eine Ganzzahl wenn möglich und sonst eine Kommazahl
+    Number(Expression),
+    /// `als Ganzzahl`, Kommazahlen werden abgeschnitten
+    Integer(Expression),
+    /// `als Kommazahl`
+    Float(Expression),
     Bool(Expression),
+    /// `als json`, ergibt eine Zeichenkette
+    Json(Expression),
+    /// `vo json`, liest eine Zeichenkette als JSON
+    FromJson(Expression),
+}
+
+/// Ein Variablenname im Programm. Der Slot ist der Index des Werts im
+/// [`crate::step3::Scope`] und wird erst von [`crate::resolve::resolve`] vergeben.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: Rc<str>,
+    pub slot: usize,
+}
+
+impl Variable {
+    /// Wie sie der Parser erstellt, noch ohne Slot
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            slot: usize::MAX,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     Atomic(Atomic),
-    Ident(String),
+    Ident(Variable),
     LogicOp(Box<LogicOp>),
+    UnaryOp(Box<UnaryOp>),
     Comparison(Box<Comparison>),
     BinaryOp(Box<BinaryOp>),
     Conditional(ConditionalExpression),
+    Match(MatchExpression),
     Input(Box<Expression>),
     Cast(Box<Cast>),
+    FieldAccess(Box<FieldAccess>),
+    Call(Box<Call>),
+    Function(Rc<FunctionDefinition>),
+    /// `lies datei "x.txt"`
+    ReadFile(Box<Expression>),
+    Random(Box<RandomRange>),
 }
 
 #[derive(Debug, Clone)]
@@ -76,7 +299,96 @@ pub enum Statement {
     Conditional(Conditional),
     Expression(Expression),
     Print(Expression),
-    Loop(Loop)
+    Loop(Loop),
+    Match(Match),
+    Try(Try),
+    Throw(Expression),
+    /// `bruuch "datei.ubpp";`
+    Import(String),
+    WriteFile(WriteFile),
+    Test(Test),
+    Expect(Expect),
+    Struct(Rc<Struct>),
+    Enum(Rc<Enum>),
+    FieldAssignment(FieldAssignment),
+}
+
+/// `definier e struktur wo Person heisst mit name, alter;`
+#[derive(Debug)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+/// Ein Wert einer [`Struct`] mit den Feldern in der Reihenfolge der Definition
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub definition: Rc<Struct>,
+    pub values: Vec<Atomic>,
+}
+
+/// `definier e uswahl wo Form heisst: Kreis(radius), Rechteck(b, h), Leer;`
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Rc<Variant>>,
+}
+
+/// Eine Variante einer [`Enum`], z.B. `Kreis(radius)`
+#[derive(Debug)]
+pub struct Variant {
+    pub name: String,
+    /// Name der [`Enum`], zu der die Variante gehört
+    pub enum_name: String,
+    pub fields: Vec<String>,
+}
+
+/// Ein Wert einer [`Variant`], z.B. `Kreis(2)`
+#[derive(Debug, Clone)]
+pub struct VariantValue {
+    pub variant: Rc<Variant>,
+    pub values: Vec<Atomic>,
+}
+
+/// `person.name = "Bea";`
+#[derive(Debug, Clone)]
+pub struct FieldAssignment {
+    pub ident: Variable,
+    pub field: String,
+    pub value: Expression,
+}
+
+/// `teste "name" { ... }`, wird nur von `ubpp test` ausgeführt
+#[derive(Debug, Clone)]
+pub struct Test {
+    pub name: String,
+    pub body: Vec<Token>,
+    /// Zeile in der Quelldatei, für die Ausgabe des Testrunners
+    pub line: usize,
+}
+
+/// `erwart bedingung;`
+#[derive(Debug, Clone)]
+pub struct Expect {
+    pub condition: Expression,
+    pub line: usize,
+}
+
+/// `schriib inhalt in datei pfad;` oder `häng inhalt a datei pfad;`
+#[derive(Debug, Clone)]
+pub struct WriteFile {
+    pub content: Expression,
+    pub path: Expression,
+    /// Bei `häng` wird an die Datei angehängt statt sie zu überschreiben
+    pub append: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Try {
+    pub body: Vec<Token>,
+    /// Name, unter dem der Fehler im `fang` Block verfügbar ist
+    pub error_ident: Variable,
+    pub catch_body: Vec<Token>,
 }
 
 #[derive(Debug, Clone)]
@@ -89,13 +401,33 @@ pub struct Loop {
 pub struct Conditional {
     pub condition: Box<Expression>,
     pub body: Vec<Token>,
+    pub else_if: Vec<ConditionalBranch>,
     pub else_body: Option<Vec<Token>>,
 }
 
+/// Ein `suscht falls` Zweig eines [`Conditional`]
+#[derive(Debug, Clone)]
+pub struct ConditionalBranch {
+    pub condition: Box<Expression>,
+    pub body: Vec<Token>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub value: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Vec<Token>,
+}
+
 #[derive(Debug, Clone)]
 pub struct VariableAssignment {
     pub new_definition: bool,
-    pub ident: String,
+    pub ident: Variable,
     pub value: Expression,
 }
 
@@ -4,273 +4,770 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::rc::Rc;
+
 use crate::step1::{
-    Atomic, BinaryOp, Cast, Comparison, Conditional, ConditionalExpression, Expression, LogicOp,
-    Loop, Statement, Token, VariableAssignment,
+    grow_stack, Atomic, BinaryOp, Call, Cast, Comparison, Conditional, ConditionalBranch,
+    ConditionalExpression, ConditionalExpressionBranch, Enum, Expect, Expression, FieldAccess,
+    FieldAssignment, FunctionDefinition, LogicOp, Loop, Match, MatchArm, MatchExpression,
+    MatchExpressionArm, Pattern, RandomRange, Statement, Struct, Test, Token, Try, UnaryOp,
+    Variable, VariableAssignment, Variant, WriteFile,
 };
 use pest::{
+    error::ErrorVariant,
     iterators::{Pair, Pairs},
-    prec_climber::{Assoc, Operator, PrecClimber},
+    pratt_parser::{Assoc, Op, PrattParser},
+    Parser, Span,
 };
 
 use super::*;
 
-pub fn parse_body(body: Pair<Rule>) -> Vec<Token> {
+/// Fehler beim Parsen. Auch ein Parse-Baum, der nicht die erwartete Form hat, ergibt einen
+/// Fehler und keinen Absturz.
+pub type ParseError = Box<pest::error::Error<Rule>>;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Die Kinder eines Knotens im Parse-Baum. Fehlt ein erwartetes Kind, gibt es einen Fehler an
+/// der Stelle des Knotens.
+struct Children<'a> {
+    span: Span<'a>,
+    rule: Rule,
+    pairs: Pairs<'a, Rule>,
+}
+
+impl<'a> Children<'a> {
+    fn of(pair: Pair<'a, Rule>) -> Self {
+        Self {
+            span: pair.as_span(),
+            rule: pair.as_rule(),
+            pairs: pair.into_inner(),
+        }
+    }
+
+    fn skip(mut self, n: usize) -> Self {
+        for _ in 0..n {
+            self.pairs.next();
+        }
+        self
+    }
+
+    fn next(&mut self) -> ParseResult<Pair<'a, Rule>> {
+        self.pairs
+            .next()
+            .ok_or_else(|| error_at(self.span, format!("incomplete {:?}", self.rule)))
+    }
+
+    fn nth(&mut self, n: usize) -> ParseResult<Pair<'a, Rule>> {
+        for _ in 0..n {
+            self.next()?;
+        }
+        self.next()
+    }
+
+    /// Die restlichen Kinder
+    fn rest(self) -> Pairs<'a, Rule> {
+        self.pairs
+    }
+}
+
+/// Das erste Kind eines Knotens
+fn first(pair: Pair<Rule>) -> ParseResult<Pair<Rule>> {
+    Children::of(pair).next()
+}
+
+fn error_at(span: Span, message: String) -> ParseError {
+    Box::new(pest::error::Error::new_from_span(
+        ErrorVariant::CustomError { message },
+        span,
+    ))
+}
+
+/// Ein Knoten, der an dieser Stelle im Parse-Baum nicht vorkommen sollte
+fn unexpected(pair: &Pair<Rule>) -> ParseError {
+    error_at(pair.as_span(), format!("unexpected {:?}", pair.as_rule()))
+}
+
+/// Wie tief Blöcke und Ausdrücke standardmässig höchstens verschachtelt sein dürfen. Tiefere
+/// Programme würden beim Aufbau und bei der Auswertung des Syntaxbaums den Stack sprengen.
+pub const MAX_NESTING: usize = 256;
+
+/// Parst ein ganzes UB++ Programm, das höchstens [`MAX_NESTING`] Ebenen tief verschachtelt ist
+pub fn parse_file(input: &str) -> ParseResult<Vec<Token>> {
+    parse_file_with_nesting(input, MAX_NESTING)
+}
+
+/// Parst ein ganzes UB++ Programm, dessen Blöcke und Ausdrücke höchstens `max_nesting` Ebenen
+/// tief verschachtelt sind
+pub fn parse_file_with_nesting(input: &str, max_nesting: usize) -> ParseResult<Vec<Token>> {
+    let file = match UBPP::parse(Rule::file, input)?.next() {
+        Some(file) => file,
+        None => return Ok(vec![]),
+    };
+    check_nesting(file.clone(), max_nesting)?;
+    parse_body(first(file)?)
+}
+
+/// Ob einer der Kommentare am Anfang der Datei `/* strikt */` ist, z.B. nach dem Dialekt-Kommentar
+pub fn has_strict_pragma(source: &str) -> bool {
+    let mut rest = source.trim_start();
+    while let Some(comment) = rest.strip_prefix("/*") {
+        let Some(end) = comment.find("*/") else {
+            return false;
+        };
+        if comment[..end].trim() == "strikt" {
+            return true;
+        }
+        rest = comment[end + 2..].trim_start();
+    }
+    false
+}
+
+/// Prüft ohne Rekursion, dass kein Block und kein Ausdruck tiefer als `max_nesting` liegt.
+/// Präfix-Operatoren und `hoch` zählen auch als Ebene, weil sie im Syntaxbaum verschachtelt sind.
+fn check_nesting(file: Pair<Rule>, max_nesting: usize) -> ParseResult<()> {
+    let mut pending = vec![(file, 0)];
+    while let Some((pair, mut depth)) = pending.pop() {
+        match pair.as_rule() {
+            Rule::body | Rule::expression => depth += 1,
+            Rule::binary_expression => {
+                depth += pair
+                    .clone()
+                    .into_inner()
+                    .filter(|op| matches!(op.as_rule(), Rule::not_op | Rule::neg_op | Rule::pow))
+                    .count()
+            }
+            _ => {}
+        }
+        if depth > max_nesting {
+            return Err(error_at(
+                pair.as_span(),
+                format!("nested deeper than {} levels", max_nesting),
+            ));
+        }
+        pending.extend(pair.into_inner().map(|inner| (inner, depth)));
+    }
+    Ok(())
+}
+
+pub fn parse_body(body: Pair<Rule>) -> ParseResult<Vec<Token>> {
+    parse_body_pairs(body.into_inner())
+}
+
+fn parse_body_pairs<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> ParseResult<Vec<Token>> {
+    grow_stack(|| parse_tokens(pairs))
+}
+
+fn parse_tokens<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> ParseResult<Vec<Token>> {
     let mut tokens = vec![];
-    for pair in body.into_inner() {
+    // Auch ein `falls` oder `lueg ob` ohne `;` am Ende des Blocks kann dessen Wert sein
+    let mut may_be_value = false;
+    for pair in pairs {
+        may_be_value = false;
         match pair.as_rule() {
             Rule::break_keyword => tokens.push(Token::Break),
             Rule::statement => {
-                let inner = pair.into_inner().next().unwrap();
+                let mut children = Children::of(pair);
+                let inner = children.next()?;
+                let has_semicolon = children.next().is_ok();
+                may_be_value = !has_semicolon
+                    && matches!(inner.as_rule(), Rule::if_statement | Rule::match_statement);
                 let stmt = match inner.as_rule() {
-                    Rule::variable_statement => as_var_assignment(inner),
-                    Rule::if_statement => as_if_statement(inner),
-                    Rule::print_statement => as_print_statement(inner),
-                    Rule::expression_statement => Token::Statement(Statement::Expression(
-                        as_expression(inner.into_inner().next().unwrap()),
-                    )),
-                    Rule::while_statement => as_while_statement(inner),
+                    Rule::variable_statement => as_var_assignment(inner)?,
+                    Rule::struct_statement => as_struct_statement(inner)?,
+                    Rule::enum_statement => as_enum_statement(inner)?,
+                    Rule::field_assignment => as_field_assignment(inner)?,
+                    Rule::if_statement => as_if_statement(inner)?,
+                    Rule::print_statement => as_print_statement(inner)?,
+                    // Ein Ausdruck ohne `;` ist ein Wert, z.B. das Resultat einer Funktion
+                    Rule::expression_statement => {
+                        let expression = as_expression(first(inner)?)?;
+                        if has_semicolon {
+                            Token::Statement(Statement::Expression(expression))
+                        } else {
+                            Token::Expression(expression)
+                        }
+                    }
+                    Rule::while_statement => as_while_statement(inner)?,
+                    Rule::match_statement => as_match_statement(inner)?,
+                    Rule::try_statement => as_try_statement(inner)?,
+                    Rule::import_statement => as_import_statement(inner)?,
+                    Rule::write_statement => as_write_statement(inner)?,
+                    Rule::test_statement => as_test_statement(inner)?,
+                    Rule::expect_statement => as_expect_statement(inner)?,
+                    Rule::throw_statement => Token::Statement(Statement::Throw(as_expression(
+                        Children::of(inner).nth(1)?,
+                    )?)),
                     _ => continue,
                 };
                 tokens.push(stmt);
             }
-            Rule::expression => {
-                tokens.push(Token::Expression(as_expression(pair)));
-            }
-            _ => unreachable!(),
+            _ => return Err(unexpected(&pair)),
+        }
+    }
+    if may_be_value {
+        if let Some(token) = tokens.pop() {
+            tokens.push(as_value(token));
         }
     }
-    tokens
-}
-
-pub fn evaluate_num_operations(pair: Pair<Rule>) -> Expression {
-    let climber = PrecClimber::new(vec![
-        Operator::new(Rule::plus, Assoc::Left) | Operator::new(Rule::minus, Assoc::Left),
-        Operator::new(Rule::mul, Assoc::Left)
-            | Operator::new(Rule::div, Assoc::Left)
-            | Operator::new(Rule::mod_op, Assoc::Left),
-        Operator::new(Rule::pow, Assoc::Right),
-    ]);
-    consume_bin_num_op(pair, &climber)
-}
-
-fn consume_bin_num_op(pair: Pair<Rule>, climber: &PrecClimber<Rule>) -> Expression {
-    let primary = |pair| consume_bin_num_op(pair, climber);
-
-    let infix = |left: Expression, op: Pair<Rule>, right: Expression| match op.as_rule() {
-        Rule::plus => Expression::BinaryOp(Box::new(BinaryOp::Plus { left, right })),
-        Rule::minus => Expression::BinaryOp(Box::new(BinaryOp::Minus { left, right })),
-        Rule::mod_op => Expression::BinaryOp(Box::new(BinaryOp::Mod { left, right })),
-        Rule::mul => Expression::BinaryOp(Box::new(BinaryOp::Mul { left, right })),
-        Rule::div => Expression::BinaryOp(Box::new(BinaryOp::Div { left, right })),
-        Rule::pow => Expression::BinaryOp(Box::new(BinaryOp::Pow { left, right })),
-        p => Expression::Atomic(Atomic::String(format!("{:?}", p))),
+    Ok(tokens)
+}
+
+fn has_value(tokens: &[Token]) -> bool {
+    matches!(tokens.last(), Some(Token::Expression(_)))
+}
+
+/// Trennt den Ausdruck am Ende eines Blocks ab. Ohne Ausdruck ist der Wert `null`.
+fn split_value(mut tokens: Vec<Token>) -> (Vec<Token>, Box<Expression>) {
+    let value = match tokens.pop() {
+        Some(Token::Expression(value)) => value,
+        last => {
+            tokens.extend(last);
+            Expression::Atomic(Atomic::Null)
+        }
     };
+    (tokens, Box::new(value))
+}
 
-    match pair.as_rule() {
-        Rule::binary_num_expression => climber.climb(pair.into_inner(), primary, infix),
-        Rule::parent_expression => consume_bin_num_op(pair.into_inner().next().unwrap(), climber),
-        Rule::rvalue_maybe_numeric => get_literal(pair.into_inner()),
-        p => unreachable!("{:?}", p),
+/// Ein `falls` mit `suscht` oder ein `lueg ob`, bei dem jeder Zweig mit einem Ausdruck endet, ist
+/// selbst ein Ausdruck. Andere Tokens bleiben unverändert.
+fn as_value(token: Token) -> Token {
+    match token {
+        Token::Statement(Statement::Conditional(conditional))
+            if has_value(&conditional.body)
+                && conditional
+                    .else_if
+                    .iter()
+                    .all(|branch| has_value(&branch.body))
+                && conditional.else_body.as_deref().is_some_and(has_value) =>
+        {
+            let (body, body_expression) = split_value(conditional.body);
+            let else_if = conditional
+                .else_if
+                .into_iter()
+                .map(|branch| {
+                    let (body, body_expression) = split_value(branch.body);
+                    ConditionalExpressionBranch {
+                        condition: branch.condition,
+                        body,
+                        body_expression,
+                    }
+                })
+                .collect();
+            let (else_body, else_body_expression) =
+                split_value(conditional.else_body.unwrap_or_default());
+            Token::Expression(Expression::Conditional(ConditionalExpression {
+                condition: conditional.condition,
+                body,
+                body_expression,
+                else_if,
+                else_body,
+                else_body_expression,
+            }))
+        }
+        Token::Statement(Statement::Match(match_statement))
+            if match_statement.arms.iter().all(|arm| has_value(&arm.body)) =>
+        {
+            let arms = match_statement
+                .arms
+                .into_iter()
+                .map(|arm| {
+                    let (body, body_expression) = split_value(arm.body);
+                    MatchExpressionArm {
+                        patterns: arm.patterns,
+                        body,
+                        body_expression,
+                    }
+                })
+                .collect();
+            Token::Expression(Expression::Match(MatchExpression {
+                value: match_statement.value,
+                arms,
+            }))
+        }
+        token => token,
     }
 }
 
-fn as_print_statement(inner: Pair<Rule>) -> Token {
-    let mut inner = inner.into_inner().skip(1);
-    let string = as_expression(inner.next().unwrap());
-    Token::Statement(Statement::Print(string))
+pub fn evaluate_operations(pair: Pair<Rule>) -> ParseResult<Expression> {
+    let pratt = PrattParser::new()
+        .op(Op::infix(Rule::or, Assoc::Left))
+        .op(Op::infix(Rule::and, Assoc::Left))
+        .op(Op::prefix(Rule::not_op))
+        .op(Op::infix(Rule::equals, Assoc::Left)
+            | Op::infix(Rule::not_equals, Assoc::Left)
+            | Op::infix(Rule::smaller_than, Assoc::Left)
+            | Op::infix(Rule::smaller_equals, Assoc::Left)
+            | Op::infix(Rule::greater_than, Assoc::Left)
+            | Op::infix(Rule::greater_equals, Assoc::Left))
+        .op(Op::infix(Rule::plus, Assoc::Left) | Op::infix(Rule::minus, Assoc::Left))
+        .op(Op::infix(Rule::mul, Assoc::Left)
+            | Op::infix(Rule::div, Assoc::Left)
+            | Op::infix(Rule::mod_op, Assoc::Left))
+        .op(Op::prefix(Rule::neg_op))
+        .op(Op::infix(Rule::pow, Assoc::Right));
+    consume_operations(pair, &pratt)
+}
+
+fn consume_operations(pair: Pair<Rule>, pratt: &PrattParser<Rule>) -> ParseResult<Expression> {
+    let prefix = |op: Pair<Rule>, operand: ParseResult<Expression>| {
+        let operand = operand?;
+        Ok(match op.as_rule() {
+            Rule::not_op => Expression::UnaryOp(Box::new(UnaryOp::Not(operand))),
+            Rule::neg_op => Expression::UnaryOp(Box::new(UnaryOp::Negate(operand))),
+            _ => return Err(unexpected(&op)),
+        })
+    };
+
+    let infix = |left: ParseResult<Expression>, op: Pair<Rule>, right: ParseResult<Expression>| {
+        let (left, right) = (left?, right?);
+        Ok(match op.as_rule() {
+            Rule::plus => Expression::BinaryOp(Box::new(BinaryOp::Plus { left, right })),
+            Rule::minus => Expression::BinaryOp(Box::new(BinaryOp::Minus { left, right })),
+            Rule::mod_op => Expression::BinaryOp(Box::new(BinaryOp::Mod { left, right })),
+            Rule::mul => Expression::BinaryOp(Box::new(BinaryOp::Mul { left, right })),
+            Rule::div => Expression::BinaryOp(Box::new(BinaryOp::Div { left, right })),
+            Rule::pow => Expression::BinaryOp(Box::new(BinaryOp::Pow { left, right })),
+            Rule::and => Expression::LogicOp(Box::new(LogicOp::And(left, right))),
+            Rule::or => Expression::LogicOp(Box::new(LogicOp::Or(left, right))),
+            _ => return as_comparison(left, right, op),
+        })
+    };
+
+    pratt
+        .map_primary(as_expression)
+        .map_prefix(prefix)
+        .map_infix(infix)
+        .parse(pair.into_inner())
+}
+
+fn as_print_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let string = as_expression(Children::of(inner).nth(1)?)?;
+    Ok(Token::Statement(Statement::Print(string)))
 }
 
-fn as_if_statement(inner: Pair<Rule>) -> Token {
-    let mut inner = inner.into_inner().skip(1);
-    let c = inner.next().unwrap().into_inner().next().unwrap();
-    let condition = as_expression(c);
-    let body = parse_body(inner.next().unwrap());
+fn as_if_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    Ok(Token::Statement(Statement::Conditional(as_conditional(
+        inner,
+    )?)))
+}
+
+fn as_conditional(inner: Pair<Rule>) -> ParseResult<Conditional> {
+    let mut inner = Children::of(inner).skip(1);
+    let condition = as_expression(first(inner.next()?)?)?;
+    let body = parse_body(inner.next()?)?;
+    let mut else_if = vec![];
     let mut else_body = None;
-    if inner.next().is_some() {
-        else_body = Some(parse_body(inner.next().unwrap()));
+    while let Ok(pair) = inner.next() {
+        match pair.as_rule() {
+            Rule::else_if_branch => else_if.push(as_else_if_branch(pair)?),
+            Rule::else_name => else_body = Some(parse_body(inner.next()?)?),
+            _ => return Err(unexpected(&pair)),
+        }
     }
-    Token::Statement(Statement::Conditional(Conditional {
+    Ok(Conditional {
         condition: Box::new(condition),
         body,
+        else_if,
         else_body,
-    }))
+    })
 }
 
-fn as_while_statement(inner: Pair<Rule>) -> Token {
-    let mut inner = inner.into_inner().skip(1);
-    let c = inner.next().unwrap().into_inner().next().unwrap();
-    let condition = as_expression(c);
-    let body = parse_body(inner.next().unwrap());
-    Token::Statement(Statement::Loop(Loop {
+fn as_else_if_branch(inner: Pair<Rule>) -> ParseResult<ConditionalBranch> {
+    let mut inner = Children::of(inner).skip(2);
+    let condition = as_expression(first(inner.next()?)?)?;
+    let body = parse_body(inner.next()?)?;
+    Ok(ConditionalBranch {
         condition: Box::new(condition),
         body,
-    }))
+    })
 }
 
-fn as_var_assignment(pair: Pair<Rule>) -> Token {
-    let inner = pair.into_inner().collect::<Vec<_>>();
-    let is_new_var = matches!(inner[0].as_rule(), Rule::let_name);
-    let ident_name = if is_new_var {
-        inner[1].as_str()
-    } else {
-        inner[0].as_str()
-    };
-    let expression = if is_new_var {
-        inner[2].clone()
-    } else {
-        inner[1].clone()
-    };
-    let expression = as_expression(expression);
-    let stmt = Statement::VariableAssignment(VariableAssignment {
-        new_definition: is_new_var,
-        ident: ident_name.to_string(),
-        value: expression,
-    });
-    Token::Statement(stmt)
+fn as_while_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(inner).skip(1);
+    let condition = as_expression(first(inner.next()?)?)?;
+    let body = parse_body(inner.next()?)?;
+    Ok(Token::Statement(Statement::Loop(Loop {
+        condition: Box::new(condition),
+        body,
+    })))
 }
 
-fn as_expression(expression: Pair<Rule>) -> Expression {
-    match expression.as_rule() {
-        Rule::if_expression => as_if_expression(expression),
-        Rule::binary_num_expression => as_binary_number_expression(expression),
-        Rule::boolean_operation => as_boolean_operation(expression),
-        Rule::boolean_expression => as_boolean_expression(expression),
-        Rule::rvalue => as_literal(expression),
-        Rule::binary_string_expression => todo! {"Not implemented"},
-        Rule::expression => {
-            let mut inner = expression.into_inner();
-            let expr = inner.next().unwrap();
-            if let Some(cast) = inner.next() {
-                as_cast(cast, as_expression(expr))
-            } else {
-                as_expression(expr)
-            }
-        }
-        Rule::input_expression => as_input_expression(expression.into_inner().nth(1).unwrap()),
-        _ => unreachable!("{:?}", expression),
-    }
+fn as_import_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let path = as_string_content(Children::of(inner).nth(1)?)?;
+    Ok(Token::Statement(Statement::Import(path)))
 }
-fn as_cast(cast: Pair<Rule>, expr: Expression) -> Expression {
-    let inner = cast
-        .into_inner()
-        .nth(1)
-        .unwrap()
-        .into_inner()
-        .next()
-        .unwrap();
-    match inner.as_rule() {
-        Rule::string => Expression::Cast(Box::new(Cast::String(expr))),
-        Rule::number => Expression::Cast(Box::new(Cast::Int(expr))),
-        Rule::bool => Expression::Cast(Box::new(Cast::Bool(expr))),
-        _ => unreachable!(),
-    }
+
+fn as_write_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(inner);
+    let append = inner.next()?.as_rule() == Rule::append_name;
+    let content = as_expression(inner.next()?)?;
+    let path = as_expression(inner.next()?)?;
+    Ok(Token::Statement(Statement::WriteFile(WriteFile {
+        content,
+        path,
+        append,
+    })))
 }
 
-fn as_input_expression(expression: Pair<Rule>) -> Expression {
-    Expression::Input(Box::new(as_expression(expression)))
+fn as_test_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let line = inner.line_col().0;
+    let mut inner = Children::of(inner).skip(1);
+    let name = as_string_content(inner.next()?)?;
+    let body = parse_body(inner.next()?)?;
+    Ok(Token::Statement(Statement::Test(Test { name, body, line })))
 }
 
-fn as_if_expression(inner: Pair<Rule>) -> Expression {
-    let mut inner = inner.into_inner().skip(1);
-    let condition = as_expression(inner.next().unwrap().into_inner().next().unwrap());
-    let body = parse_body(inner.next().unwrap());
-    let body_expression = as_expression(inner.next().unwrap().into_inner().next().unwrap());
-    inner.next();
-    let else_body = parse_body(inner.next().unwrap());
-    let else_expression = Box::new(as_expression(
-        inner.next().unwrap().into_inner().next().unwrap(),
-    ));
+fn as_expect_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let line = inner.line_col().0;
+    let condition = as_expression(Children::of(inner).nth(1)?)?;
+    Ok(Token::Statement(Statement::Expect(Expect {
+        condition,
+        line,
+    })))
+}
 
-    Expression::Conditional(ConditionalExpression {
-        condition: Box::new(condition),
+fn as_try_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(inner).skip(1);
+    let body = parse_body(inner.next()?)?;
+    let error_ident = Variable::new(inner.nth(1)?.as_str());
+    let catch_body = parse_body(inner.next()?)?;
+    Ok(Token::Statement(Statement::Try(Try {
         body,
-        body_expression: Box::new(body_expression),
-        else_body,
-        else_body_expression: else_expression,
+        error_ident,
+        catch_body,
+    })))
+}
+
+fn as_match_statement(inner: Pair<Rule>) -> ParseResult<Token> {
+    Ok(Token::Statement(Statement::Match(as_match(inner)?)))
+}
+
+fn as_match(inner: Pair<Rule>) -> ParseResult<Match> {
+    let mut inner = Children::of(inner).skip(1);
+    let value = as_expression(inner.next()?)?;
+    let arms = inner
+        .rest()
+        .map(|arm| {
+            let mut arm = Children::of(arm);
+            let patterns = as_patterns(arm.next()?)?;
+            let body = arm.next()?;
+            let body = match body.as_rule() {
+                Rule::body => parse_body(body)?,
+                Rule::statement => parse_body_pairs(std::iter::once(body))?,
+                _ => return Err(unexpected(&body)),
+            };
+            Ok(MatchArm { patterns, body })
+        })
+        .collect::<ParseResult<_>>()?;
+    Ok(Match {
+        value: Box::new(value),
+        arms,
     })
 }
 
-fn as_binary_number_expression(pair: Pair<Rule>) -> Expression {
-    evaluate_num_operations(pair)
+fn as_match_expression(inner: Pair<Rule>) -> ParseResult<Expression> {
+    let span = inner.as_span();
+    match as_value(Token::Statement(Statement::Match(as_match(inner)?))) {
+        Token::Expression(value) => Ok(value),
+        _ => Err(error_at(
+            span,
+            "every arm of `lueg ob` needs a value".to_string(),
+        )),
+    }
 }
 
-fn as_boolean_operation(pair: Pair<Rule>) -> Expression {
-    let mut inner = pair.into_inner();
-    let lhs = inner.next().unwrap();
-    let op = inner.next().unwrap();
-    let rhs = inner.next().unwrap();
-    let lhs = match lhs.as_rule() {
-        Rule::rvalue => as_literal(lhs),
-        Rule::boolean_expression => as_boolean_expression(lhs),
-        _ => unreachable!(),
-    };
-    let rhs = match rhs.as_rule() {
-        Rule::rvalue => as_literal(rhs),
-        Rule::boolean_expression => as_boolean_expression(rhs),
-        _ => unreachable!(),
-    };
-    match op.as_rule() {
-        Rule::and => Expression::LogicOp(Box::new(LogicOp::And(lhs, rhs))),
-        Rule::or => Expression::LogicOp(Box::new(LogicOp::Or(lhs, rhs))),
-        _ => unreachable!(),
+fn as_patterns(pair: Pair<Rule>) -> ParseResult<Vec<Pattern>> {
+    let mut patterns = vec![];
+    for pattern in pair.into_inner() {
+        match pattern.as_rule() {
+            Rule::default_pattern => patterns.push(Pattern::Default),
+            Rule::or => {}
+            Rule::pattern => {
+                let inner = first(pattern)?;
+                patterns.push(match inner.as_rule() {
+                    Rule::literal_pattern => Pattern::Literal(as_pattern_literal(inner)?),
+                    Rule::range_pattern => {
+                        let mut bounds = Children::of(inner);
+                        let from = as_pattern_literal(bounds.next()?)?;
+                        let to = as_pattern_literal(bounds.next()?)?;
+                        Pattern::Range(from, to)
+                    }
+                    Rule::variant_pattern => {
+                        let mut inner = Children::of(inner);
+                        let name = Variable::new(inner.next()?.as_str());
+                        let bindings = unique_names(inner.rest(), "binding")?
+                            .iter()
+                            .map(|binding| Variable::new(binding))
+                            .collect();
+                        Pattern::Variant { name, bindings }
+                    }
+                    _ => return Err(unexpected(&inner)),
+                })
+            }
+            _ => return Err(unexpected(&pattern)),
+        }
     }
+    Ok(patterns)
 }
 
-fn as_boolean_expression(pair: Pair<Rule>) -> Expression {
-    let mut inner = pair.into_inner();
-    let lhs = as_literal(inner.next().unwrap());
-    let comparison = inner.next().unwrap();
-    let rhs = as_literal(inner.next().unwrap());
-    as_comparison(lhs, rhs, comparison)
+fn as_pattern_literal(pair: Pair<Rule>) -> ParseResult<Atomic> {
+    let span = pair.as_span();
+    match as_literal(pair)? {
+        Expression::Atomic(atomic) => Ok(atomic),
+        _ => Err(error_at(span, "expected a literal".to_string())),
+    }
 }
 
-fn as_comparison(lhs: Expression, rhs: Expression, pair: Pair<Rule>) -> Expression {
-    match pair.into_inner().next().unwrap().as_rule() {
-        Rule::smaller_than => Expression::Comparison(Box::new(Comparison::Smaller(lhs, rhs))),
-        Rule::smaller_equals => {
-            Expression::Comparison(Box::new(Comparison::SmallerEquals(lhs, rhs)))
+fn as_var_assignment(pair: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(pair);
+    let first = inner.next()?;
+    let is_new_var = matches!(first.as_rule(), Rule::let_name);
+    let ident_name = if is_new_var { inner.next()? } else { first };
+    let expression = as_expression(inner.next()?)?;
+    let stmt = Statement::VariableAssignment(VariableAssignment {
+        new_definition: is_new_var,
+        ident: Variable::new(ident_name.as_str()),
+        value: expression,
+    });
+    Ok(Token::Statement(stmt))
+}
+
+fn as_struct_statement(pair: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(pair).skip(1);
+    let name = inner.next()?.as_str().to_string();
+    let fields = unique_names(inner.rest(), "field")?;
+    Ok(Token::Statement(Statement::Struct(Rc::new(Struct {
+        name,
+        fields,
+    }))))
+}
+
+fn as_enum_statement(pair: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(pair).skip(1);
+    let name = inner.next()?.as_str().to_string();
+    let mut variants: Vec<Rc<Variant>> = vec![];
+    for variant in inner.rest() {
+        let span = variant.as_span();
+        let mut variant = Children::of(variant);
+        let variant_name = variant.next()?.as_str();
+        if variants.iter().any(|v| v.name == variant_name) {
+            return Err(error_at(
+                span,
+                format!("duplicate variant `{}`", variant_name),
+            ));
         }
-        Rule::equals => Expression::Comparison(Box::new(Comparison::Equals(lhs, rhs))),
-        Rule::greater_equals => {
-            Expression::Comparison(Box::new(Comparison::GreaterEquals(lhs, rhs)))
+        variants.push(Rc::new(Variant {
+            name: variant_name.to_string(),
+            enum_name: name.clone(),
+            fields: unique_names(variant.rest(), "field")?,
+        }));
+    }
+    Ok(Token::Statement(Statement::Enum(Rc::new(Enum {
+        name,
+        variants,
+    }))))
+}
+
+/// Namen von Feldern, Parametern usw., die je nur einmal vorkommen dürfen
+fn unique_names(pairs: Pairs<Rule>, what: &str) -> ParseResult<Vec<String>> {
+    let mut names: Vec<String> = vec![];
+    for pair in pairs {
+        if names.iter().any(|name| name == pair.as_str()) {
+            return Err(error_at(
+                pair.as_span(),
+                format!("duplicate {} `{}`", what, pair.as_str()),
+            ));
         }
-        Rule::greater_than => Expression::Comparison(Box::new(Comparison::Greater(lhs, rhs))),
-        _ => unreachable!(),
+        names.push(pair.as_str().to_string());
     }
+    Ok(names)
 }
 
-fn as_literal(pair: Pair<Rule>) -> Expression {
-    let inner = pair.into_inner().next().unwrap();
-    match inner.as_rule() {
-        Rule::boolean_literal => match inner.into_inner().next().unwrap().as_rule() {
-            Rule::true_literal => Expression::Atomic(Atomic::Bool(true)),
-            Rule::false_literal => Expression::Atomic(Atomic::Bool(false)),
-            _ => unreachable!(),
+fn as_field_assignment(pair: Pair<Rule>) -> ParseResult<Token> {
+    let mut inner = Children::of(pair);
+    let mut target = Children::of(inner.next()?);
+    let ident = Variable::new(target.next()?.as_str());
+    let field = target.next()?.as_str().to_string();
+    let value = as_expression(inner.next()?)?;
+    Ok(Token::Statement(Statement::FieldAssignment(
+        FieldAssignment {
+            ident,
+            field,
+            value,
         },
-        Rule::string_literal => {
-            let inner = inner.into_inner().nth(1).unwrap().as_str().to_string();
-            Expression::Atomic(Atomic::String(inner))
+    )))
+}
+
+fn as_expression(expression: Pair<Rule>) -> ParseResult<Expression> {
+    grow_stack(|| match expression.as_rule() {
+        Rule::if_expression => as_if_expression(expression),
+        Rule::match_expression => as_match_expression(expression),
+        Rule::binary_expression => evaluate_operations(expression),
+        Rule::operand | Rule::parent_expression => as_expression(first(expression)?),
+        Rule::rvalue => as_literal(expression),
+        Rule::call => as_call(expression),
+        Rule::function_expression => as_function(expression),
+        Rule::expression => {
+            let mut inner = Children::of(expression);
+            let expr = inner.next()?;
+            if let Ok(cast) = inner.next() {
+                if cast.as_rule() == Rule::from_json {
+                    return Ok(Expression::Cast(Box::new(Cast::FromJson(as_expression(
+                        expr,
+                    )?))));
+                }
+                as_cast(cast, as_expression(expr)?)
+            } else {
+                as_expression(expr)
+            }
         }
-        Rule::variable_name => Expression::Ident(inner.as_str().to_string()),
-        Rule::numeric_literal => {
-            Expression::Atomic(Atomic::Number(inner.as_str().trim().parse().unwrap()))
+        Rule::input_expression => as_input_expression(Children::of(expression).nth(1)?),
+        Rule::random_expression => {
+            let mut inner = Children::of(expression).skip(1);
+            let from = as_expression(inner.next()?)?;
+            let to = as_expression(inner.next()?)?;
+            Ok(Expression::Random(Box::new(RandomRange { from, to })))
         }
-        Rule::expression => as_expression(inner),
-        p => unreachable!("{:?}", p),
-    }
+        Rule::read_file_expression => Ok(Expression::ReadFile(Box::new(as_expression(
+            Children::of(expression).nth(1)?,
+        )?))),
+        _ => Err(unexpected(&expression)),
+    })
+}
+fn as_cast(cast: Pair<Rule>, expr: Expression) -> ParseResult<Expression> {
+    let inner = first(Children::of(cast).nth(1)?)?;
+    let cast = match inner.as_rule() {
+        Rule::string => Cast::String(expr),
+        Rule::number => Cast::Number(expr),
+        Rule::integer => Cast::Integer(expr),
+        Rule::float => Cast::Float(expr),
+        Rule::bool => Cast::Bool(expr),
+        Rule::json => Cast::Json(expr),
+        _ => return Err(unexpected(&inner)),
+    };
+    Ok(Expression::Cast(Box::new(cast)))
 }
 
-fn get_literal(mut pair: Pairs<Rule>) -> Expression {
-    let element: Pair<Rule> = pair.next().unwrap();
-    match element.as_rule() {
-        Rule::numeric_literal => Expression::Atomic(Atomic::Number(
-            element.as_str().trim().parse::<f64>().unwrap(),
+fn as_input_expression(expression: Pair<Rule>) -> ParseResult<Expression> {
+    Ok(Expression::Input(Box::new(as_expression(expression)?)))
+}
+
+fn as_if_expression(inner: Pair<Rule>) -> ParseResult<Expression> {
+    let span = inner.as_span();
+    match as_value(Token::Statement(Statement::Conditional(as_conditional(
+        inner,
+    )?))) {
+        Token::Expression(value) => Ok(value),
+        _ => Err(error_at(
+            span,
+            "every branch of `falls` needs a value".to_string(),
         )),
-        Rule::variable_name => Expression::Ident(element.as_str().to_string()),
-        p => {
-            println!("{:?}", p);
-            unreachable!()
+    }
+}
+
+fn as_comparison(lhs: Expression, rhs: Expression, pair: Pair<Rule>) -> ParseResult<Expression> {
+    let comparison = match pair.as_rule() {
+        Rule::smaller_than => Comparison::Smaller(lhs, rhs),
+        Rule::smaller_equals => Comparison::SmallerEquals(lhs, rhs),
+        Rule::equals => Comparison::Equals(lhs, rhs),
+        Rule::not_equals => Comparison::NotEquals(lhs, rhs),
+        Rule::greater_equals => Comparison::GreaterEquals(lhs, rhs),
+        Rule::greater_than => Comparison::Greater(lhs, rhs),
+        _ => return Err(unexpected(&pair)),
+    };
+    Ok(Expression::Comparison(Box::new(comparison)))
+}
+
+fn as_literal(pair: Pair<Rule>) -> ParseResult<Expression> {
+    let inner = first(pair)?;
+    Ok(match inner.as_rule() {
+        Rule::boolean_literal => {
+            let literal = first(inner)?;
+            match literal.as_rule() {
+                Rule::true_literal => Expression::Atomic(Atomic::Bool(true)),
+                Rule::false_literal => Expression::Atomic(Atomic::Bool(false)),
+                _ => return Err(unexpected(&literal)),
+            }
         }
+        Rule::string_literal => Expression::Atomic(Atomic::String(as_string_content(inner)?)),
+        Rule::variable_name => Expression::Ident(Variable::new(inner.as_str())),
+        Rule::field_access => as_field_access(inner)?,
+        Rule::numeric_literal => Expression::Atomic(as_numeric_literal(inner)?),
+        Rule::expression => as_expression(inner)?,
+        _ => return Err(unexpected(&inner)),
+    })
+}
+
+/// Der Text zwischen den Anführungszeichen eines `string_literal`, `\"` und `\\` ersetzt
+fn as_string_content(pair: Pair<Rule>) -> ParseResult<String> {
+    let content = Children::of(pair).nth(1)?;
+    let mut text = String::with_capacity(content.as_str().len());
+    let mut chars = content.as_str().chars();
+    while let Some(c) = chars.next() {
+        // Die Grammatik lässt nach einem Backslash nur `"` oder `\` zu
+        text.extend(if c == '\\' { chars.next() } else { Some(c) });
     }
+    Ok(text)
+}
+
+fn as_field_access(pair: Pair<Rule>) -> ParseResult<Expression> {
+    let mut inner = Children::of(pair);
+    let value = Expression::Ident(Variable::new(inner.next()?.as_str()));
+    let field = inner.next()?.as_str().to_string();
+    Ok(Expression::FieldAccess(Box::new(FieldAccess {
+        value,
+        field,
+    })))
+}
+
+fn as_call(pair: Pair<Rule>) -> ParseResult<Expression> {
+    let mut inner = Children::of(pair);
+    let name = inner.next()?;
+    let function = match name.as_rule() {
+        Rule::field_access => as_field_access(name)?,
+        _ => Expression::Ident(Variable::new(name.as_str())),
+    };
+    let arguments = inner
+        .rest()
+        .map(as_expression)
+        .collect::<ParseResult<_>>()?;
+    Ok(Expression::Call(Box::new(Call {
+        function,
+        arguments,
+    })))
+}
+
+fn as_function(pair: Pair<Rule>) -> ParseResult<Expression> {
+    let mut inner = Children::of(pair).skip(1);
+    let parameters = unique_names(inner.next()?.into_inner(), "parameter")?
+        .iter()
+        .map(|parameter| Variable::new(parameter))
+        .collect();
+    let body = parse_body(inner.next()?)?;
+    let (body, body_expression) = if has_value(&body) {
+        let (body, body_expression) = split_value(body);
+        (body, Some(*body_expression))
+    } else {
+        (body, None)
+    };
+    Ok(Expression::Function(Rc::new(FunctionDefinition {
+        parameters,
+        body,
+        body_expression,
+        captures: vec![],
+        own_slot: None,
+    })))
+}
+
+/// Zahlen ohne Dezimalpunkt werden zu Ganzzahlen. Die Grammatik erlaubt alle Unicode-Ziffern,
+/// gerechnet wird aber nur mit `0` bis `9`.
+fn as_numeric_literal(pair: Pair<Rule>) -> ParseResult<Atomic> {
+    let literal = pair.as_str().trim();
+    let number = if literal.contains('.') {
+        literal.parse().map(Atomic::Number).ok()
+    } else {
+        literal.parse().map(Atomic::Integer).ok()
+    };
+    number.ok_or_else(|| error_at(pair.as_span(), format!("invalid number `{}`", literal)))
 }
@@ -4,83 +4,454 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::{collections::HashMap, io::stdin};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    io::stdin,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use chrono::NaiveDateTime;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use pest::error::LineColLocation;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
 use crate::step1::{
-    Atomic, BinaryOp, Comparison, ConditionalExpression, Expression, LogicOp, Loop, Statement,
-    Token,
+    grow_stack, Atomic, BinaryOp, Call, Closure, Comparison, ConditionalExpression, ErrorKind,
+    Expect, Expression, FieldAccess, FieldAssignment, FunctionDefinition, LogicOp, Loop, Match,
+    MatchExpression, Module, Pattern, Record, RuntimeError, Statement, Struct, Token, Try, UnaryOp,
+    Variable, Variant, VariantValue,
+};
+use crate::{
+    builtins::{self, Permissions},
+    dialect::Dialect,
+    resolve::{resolve, resolve_tokens, Names, Program},
+    step2::{has_strict_pragma, MAX_NESTING},
 };
 
+/// Verschachtelungstiefe, wenn [`Scope::depth_limit`] nicht gesetzt ist
+pub const MAX_DEPTH: usize = 5_000;
+
+/// Grösstes Resultat von `hoch` mit Ganzzahlen in Bits, statt minutenlang zu rechnen
+const MAX_POW_BITS: u64 = 1 << 20;
+
+/// Zustand, der durch die ganze Auswertung gereicht wird
+#[derive(Debug, Default)]
+pub struct Scope {
+    /// Werte der Variablen nach Slot, `None` für Variablen ohne Wert. Die Slots vergibt
+    /// [`resolve`].
+    variables: Vec<Option<Atomic>>,
+    pub(crate) names: Names,
+    /// Datei, die gerade ausgeführt wird. `bruuch` und die Dateifunktionen suchen relativ dazu.
+    pub path: Option<PathBuf>,
+    /// Freigegebene Pfade für `lies datei` und `schriib`
+    pub permissions: Permissions,
+    /// Fixe Zeit für `jetzt()`, z.B. in Tests. Sonst gilt die Systemzeit.
+    pub now: Option<NaiveDateTime>,
+    /// Seed für den Zufallsgenerator. Ohne Seed ist jeder Lauf anders.
+    pub seed: Option<u64>,
+    /// Maximale Anzahl Schritte, z.B. beim Fuzzing. Ohne Limit läuft ein Programm beliebig lange.
+    pub step_limit: Option<u64>,
+    /// Maximale Verschachtelung von Blöcken, Ausdrücken und Funktionsaufrufen, d.h. vor allem die
+    /// Rekursionstiefe. Ohne Angabe gilt [`MAX_DEPTH`].
+    pub depth_limit: Option<usize>,
+    /// Maximale Verschachtelung beim Parsen von Modulen mit `bruuch`. Ohne Angabe gilt
+    /// [`MAX_NESTING`].
+    pub nesting_limit: Option<usize>,
+    /// Im strikten Modus wird nichts implizit umgewandelt, z.B. mit `/* strikt */` am Anfang der
+    /// Datei. Umgewandelt wird dann nur mit `als`.
+    pub strict: bool,
+    /// Ziel von `gib us` und Quelle von `e frog`, z.B. eine Jupyter-Zelle. Ohne Konsole gelten
+    /// Standardausgabe und -eingabe.
+    pub console: Option<Box<dyn Console>>,
+    steps: u64,
+    depth: usize,
+    rng: Option<ChaCha8Rng>,
+    modules: HashMap<PathBuf, ModuleState>,
+}
+
+impl Scope {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Relative Pfade gelten ab dem Verzeichnis der Datei, die gerade ausgeführt wird
+    pub(crate) fn relative_path(&self, path: &str) -> PathBuf {
+        let base = self.path.as_deref().and_then(Path::parent);
+        base.unwrap_or_else(|| Path::new(".")).join(path)
+    }
+}
+
+/// Ein- und Ausgabe eines Programms
+pub trait Console {
+    fn print(&mut self, line: &str);
+    /// Fragt mit `prompt` nach einer Zeile. Wie bei der Standardeingabe endet sie mit `\n`.
+    fn input(&mut self, prompt: &str) -> std::io::Result<String>;
+}
+
+impl fmt::Debug for dyn Console {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Console")
+    }
+}
+
+struct StdConsole;
+
+impl Console for StdConsole {
+    fn print(&mut self, line: &str) {
+        println!("{}", line);
+    }
+
+    fn input(&mut self, prompt: &str) -> std::io::Result<String> {
+        println!("{}", prompt);
+        let mut line = String::new();
+        stdin().read_line(&mut line)?;
+        Ok(line)
+    }
+}
+
+impl Scope {
+    fn console(&mut self) -> &mut dyn Console {
+        self.console
+            .get_or_insert_with(|| Box::new(StdConsole))
+            .as_mut()
+    }
+
+    /// Der Zufallsgenerator wird beim ersten Gebrauch erstellt, damit ein später gesetzter
+    /// Seed noch berücksichtigt wird. Anders als `StdRng` ist ChaCha8 fest definiert, ein Seed
+    /// gibt darum auch mit einer neueren Version von `rand` die gleichen Zahlen.
+    pub(crate) fn rng(&mut self) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.rng.get_or_insert_with(|| match seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => rand::make_rng(),
+        })
+    }
+
+    pub(crate) fn is_defined(&self, slot: usize) -> bool {
+        self.value(slot).is_some()
+    }
+
+    fn value(&self, slot: usize) -> Option<&Atomic> {
+        self.variables.get(slot)?.as_ref()
+    }
+
+    /// Setzt den Wert der Variable, `None` löscht sie. Gibt den bisherigen Wert zurück.
+    fn replace(&mut self, slot: usize, value: Option<Atomic>) -> Option<Atomic> {
+        if slot >= self.variables.len() {
+            self.variables.resize(self.names.len(), None);
+        }
+        std::mem::replace(&mut self.variables[slot], value)
+    }
+
+    /// Alle Variablen, die einen Wert haben
+    fn defined(&self) -> impl Iterator<Item = (&str, &Atomic)> {
+        self.variables
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, value)| Some((self.names.name(slot).as_ref(), value.as_ref()?)))
+    }
+
+    /// Die Namen aller Variablen, die einen Wert haben, z.B. für die Vervollständigung
+    pub fn variable_names(&self) -> impl Iterator<Item = &str> {
+        self.defined().map(|(name, _)| name)
+    }
+
+    /// Zählt einen Schritt, d.h. eine Anweisung oder einen Schleifendurchlauf
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        match self.step_limit {
+            Some(limit) if self.steps > limit => Err(RuntimeError::new(
+                ErrorKind::Limit,
+                format!("Step limit of {} reached", limit),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Führt `f` eine Ebene tiefer aus. Weil der Stack bei Bedarf auf dem Heap wächst, begrenzt
+    /// nur `depth_limit` die Tiefe, und auch endlose Rekursion endet mit einem `limit` Fehler.
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        let limit = self.depth_limit.unwrap_or(MAX_DEPTH);
+        if self.depth >= limit {
+            return Err(RuntimeError::new(
+                ErrorKind::Limit,
+                format!("Depth limit of {} reached", limit),
+            ));
+        }
+        self.depth += 1;
+        let result = grow_stack(|| f(self));
+        self.depth -= 1;
+        result
+    }
+}
+
+#[derive(Debug)]
+enum ModuleState {
+    /// Das Modul wird gerade ausgeführt, ein weiterer Import wäre ein Zyklus
+    Loading,
+    Loaded(Module),
+}
+
 impl Expression {
-    fn as_bool(&self, global_scope: &mut HashMap<String, Expression>) -> Result<bool, String> {
-        let inner = eval_expression(self, global_scope)?;
-        match inner {
-            Atomic::String(s) => Ok(s.parse().map_err(|e| format!("{:?}", e))?),
+    fn as_bool(&self, global_scope: &mut Scope) -> Result<bool, RuntimeError> {
+        let value = eval_expression(self, global_scope)?;
+        global_scope.coerce_bool(value)
+    }
+    fn as_string(&self, global_scope: &mut Scope) -> Result<String, RuntimeError> {
+        let value = eval_expression(self, global_scope)?;
+        global_scope.coerce_string(value)
+    }
+}
+
+/// Implizite Umwandlungen, z.B. für Bedingungen, Operatoren und die Argumente eingebauter
+/// Funktionen. Im strikten Modus muss der Wert den verlangten Typ schon haben.
+impl Scope {
+    pub(crate) fn check_strict(
+        &self,
+        value: &Atomic,
+        expected: &str,
+        ok: bool,
+    ) -> Result<(), RuntimeError> {
+        if self.strict && !ok {
+            return Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Cannot use `{}` as {} in strict mode", value, expected),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn coerce_bool(&self, value: Atomic) -> Result<bool, RuntimeError> {
+        self.check_strict(&value, "bool", matches!(value, Atomic::Bool(_)))?;
+        value.into_bool()
+    }
+
+    pub(crate) fn coerce_string(&self, value: Atomic) -> Result<String, RuntimeError> {
+        self.check_strict(&value, "string", matches!(value, Atomic::String(_)))?;
+        value.into_string()
+    }
+
+    pub(crate) fn coerce_num(&self, value: Atomic) -> Result<f64, RuntimeError> {
+        self.coerce_numeric(value)?.into_num()
+    }
+
+    /// Ganzzahlen und Kommazahlen gelten auch im strikten Modus beide als Zahl
+    pub(crate) fn coerce_numeric(&self, value: Atomic) -> Result<Atomic, RuntimeError> {
+        let is_number = matches!(value, Atomic::Integer(_) | Atomic::Number(_));
+        self.check_strict(&value, "number", is_number)?;
+        value.into_numeric()
+    }
+
+    pub(crate) fn coerce_integer(&self, value: Atomic) -> Result<BigInt, RuntimeError> {
+        self.check_strict(&value, "integer", matches!(value, Atomic::Integer(_)))?;
+        value.into_integer()
+    }
+}
+
+impl Atomic {
+    pub(crate) fn into_bool(self) -> Result<bool, RuntimeError> {
+        match self {
+            Atomic::String(s) => Ok(s.parse().map_err(|e| {
+                RuntimeError::new(ErrorKind::Type, format!("`{}` is not a bool ({})", s, e))
+            })?),
             Atomic::Number(i) => Ok(i == 0.0),
+            Atomic::Integer(i) => Ok(i.is_zero()),
             Atomic::Bool(b) => Ok(b),
             Atomic::Null => Ok(false),
-            Atomic::Interrupt => unreachable!(),
+            Atomic::Error(_)
+            | Atomic::Module(_)
+            | Atomic::Builtin(_)
+            | Atomic::Function(_)
+            | Atomic::Struct(_)
+            | Atomic::Record(_)
+            | Atomic::Variant(_)
+            | Atomic::VariantValue(_)
+            | Atomic::List(_)
+            | Atomic::Map(_)
+            | Atomic::Date(_)
+            | Atomic::Interrupt => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Cannot use `{}` as bool", self),
+            )),
         }
     }
-    fn as_string(&self, global_scope: &mut HashMap<String, Expression>) -> Result<String, String> {
-        let inner = eval_expression(self, global_scope)?;
-        match inner {
+    pub(crate) fn into_string(self) -> Result<String, RuntimeError> {
+        match self {
             Atomic::String(s) => Ok(s),
-            Atomic::Number(i) => Ok(i.to_string()),
+            number @ (Atomic::Number(_) | Atomic::Integer(_)) => Ok(number.to_string()),
             Atomic::Bool(b) => Ok(b.to_string()),
             Atomic::Null => Ok("null".to_string()),
-            Atomic::Interrupt => unreachable!(),
+            Atomic::Error(_)
+            | Atomic::Module(_)
+            | Atomic::Builtin(_)
+            | Atomic::Function(_)
+            | Atomic::Struct(_)
+            | Atomic::Record(_)
+            | Atomic::Variant(_)
+            | Atomic::VariantValue(_)
+            | Atomic::List(_)
+            | Atomic::Map(_)
+            | Atomic::Date(_) => Ok(self.to_string()),
+            Atomic::Interrupt => Err(RuntimeError::new(
+                ErrorKind::Type,
+                "Cannot use `stop` as string",
+            )),
         }
     }
-    fn as_num(&self, global_scope: &mut HashMap<String, Expression>) -> Result<f64, String> {
-        let inner = eval_expression(self, global_scope)?;
-        match inner {
-            Atomic::String(s) => Ok(s.trim().parse().map_err(|e| format!("{:?}", e))?),
-            Atomic::Number(i) => Ok(i),
-            Atomic::Bool(b) => Ok(b as i32 as f64),
-            Atomic::Null => Ok(0.0),
-            Atomic::Interrupt => unreachable!(),
+    pub(crate) fn into_num(self) -> Result<f64, RuntimeError> {
+        match self.into_numeric()? {
+            Atomic::Integer(i) => Ok(i.to_f64().unwrap_or(f64::NAN)),
+            Atomic::Number(n) => Ok(n),
+            other => Err(not_a_number(other)),
         }
     }
+    /// Wandelt den Wert in eine Zahl um, wobei Ganzzahlen erhalten bleiben.
+    /// Das Resultat ist immer entweder [`Atomic::Integer`] oder [`Atomic::Number`].
+    pub(crate) fn into_numeric(self) -> Result<Atomic, RuntimeError> {
+        match self {
+            Atomic::String(s) => {
+                let s = s.trim();
+                if let Ok(i) = s.parse::<BigInt>() {
+                    return Ok(Atomic::Integer(i));
+                }
+                Ok(Atomic::Number(s.parse().map_err(|e| {
+                    RuntimeError::new(ErrorKind::Type, format!("`{}` is not a number ({})", s, e))
+                })?))
+            }
+            Atomic::Number(n) => Ok(Atomic::Number(n)),
+            Atomic::Integer(i) => Ok(Atomic::Integer(i)),
+            Atomic::Bool(b) => Ok(Atomic::Integer(BigInt::from(b as i32))),
+            Atomic::Null => Ok(Atomic::Integer(BigInt::zero())),
+            Atomic::Error(_)
+            | Atomic::Module(_)
+            | Atomic::Builtin(_)
+            | Atomic::Function(_)
+            | Atomic::Struct(_)
+            | Atomic::Record(_)
+            | Atomic::Variant(_)
+            | Atomic::VariantValue(_)
+            | Atomic::List(_)
+            | Atomic::Map(_)
+            | Atomic::Date(_)
+            | Atomic::Interrupt => Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Cannot use `{}` as number", self),
+            )),
+        }
+    }
+    pub(crate) fn into_integer(self) -> Result<BigInt, RuntimeError> {
+        match self.into_numeric()? {
+            Atomic::Integer(i) => Ok(i),
+            Atomic::Number(n) => BigInt::from_f64(n.trunc()).ok_or_else(|| {
+                RuntimeError::new(
+                    ErrorKind::Type,
+                    format!("`{}` cannot be converted to an integer", n),
+                )
+            }),
+            other => Err(not_a_number(other)),
+        }
+    }
+}
+
+/// Für Werte, die [`Atomic::into_numeric`] nicht in eine Zahl umwandeln konnte
+pub(crate) fn not_a_number(value: Atomic) -> RuntimeError {
+    RuntimeError::new(ErrorKind::Type, format!("Cannot use `{}` as number", value))
 }
 
-pub fn eval_tokens(
+impl Record {
+    /// Position des Felds in [`Record::values`]
+    fn field_index(&self, field: &str) -> Option<usize> {
+        self.definition.fields.iter().position(|f| f == field)
+    }
+}
+
+/// Zwei Zahlen als Operanden einer Rechnung. Nur wenn beide Ganzzahlen sind,
+/// wird exakt gerechnet.
+enum Operands {
+    Integer(BigInt, BigInt),
+    Float(f64, f64),
+}
+
+impl Operands {
+    fn new(left: Atomic, right: Atomic) -> Result<Self, RuntimeError> {
+        match (left.into_numeric()?, right.into_numeric()?) {
+            (Atomic::Integer(l), Atomic::Integer(r)) => Ok(Operands::Integer(l, r)),
+            (l, r) => Ok(Operands::Float(l.into_num()?, r.into_num()?)),
+        }
+    }
+
+    /// Für `durch` und `rest`, auch bei Kommazahlen gibt es weder `inf` noch `NaN`
+    fn divisor_is_zero(&self) -> bool {
+        match self {
+            Operands::Integer(_, r) => r.is_zero(),
+            Operands::Float(_, r) => *r == 0.0,
+        }
+    }
+}
+
+/// Wertet ein Programm einmal aus. Vorher werden die Variablen mit [`resolve`] aufgelöst, so dass
+/// auch Namen, die nirgends definiert werden, ein Fehler sind.
+pub fn eval_tokens(tokens: Vec<Token>, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    let program = resolve(tokens, global_scope)?;
+    eval_block(program.tokens(), global_scope)
+}
+
+/// Wertet ein aufgelöstes Programm aus, z.B. mehrmals in je einem frischen Scope. Passen die
+/// Slots nicht zu den Namen des Scopes, wird eine Kopie neu aufgelöst.
+pub fn eval_program(program: &Program, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    if global_scope.names.adopt(program.names()) {
+        eval_block(program.tokens(), global_scope)
+    } else {
+        eval_tokens(program.tokens().to_vec(), global_scope)
+    }
+}
+
+/// Wertet aufgelöste Tokens aus, z.B. einen Block
+pub(crate) fn eval_block(
     tokens: &[Token],
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
-    let mut last_expression = Atomic::Null;
-    for token in tokens {
-        match token {
-            Token::Expression(e) => {
-                last_expression = eval_expression(e, global_scope)?;
-                if matches!(last_expression, Atomic::Interrupt) {
-                    return Ok(last_expression);
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    global_scope.nested(|global_scope| {
+        let mut last_expression = Atomic::Null;
+        for token in tokens {
+            global_scope.step()?;
+            match token {
+                Token::Expression(e) => {
+                    last_expression = eval_expression(e, global_scope)?;
+                    if matches!(last_expression, Atomic::Interrupt) {
+                        return Ok(last_expression);
+                    }
                 }
-            }
-            Token::Statement(stmt) => {
-                let is_interrupt = eval_statement(stmt, global_scope)?;
-                if matches!(is_interrupt, Atomic::Interrupt) {
-                    return Ok(is_interrupt);
+                Token::Statement(stmt) => {
+                    let is_interrupt = eval_statement(stmt, global_scope)?;
+                    if matches!(is_interrupt, Atomic::Interrupt) {
+                        return Ok(is_interrupt);
+                    }
                 }
+                Token::Break => {
+                    return Ok(Atomic::Interrupt);
+                }
+                Token::Return => return Ok(Atomic::Interrupt),
             }
-            Token::Break => {
-                return Ok(Atomic::Interrupt);
-            },
-            Token::Return => return Ok(Atomic::Interrupt),
         }
-    }
-    Ok(last_expression)
+        Ok(last_expression)
+    })
 }
 
-fn eval_statement(
-    stmt: &Statement,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
+fn eval_statement(stmt: &Statement, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
     match stmt {
-        Statement::VariableAssignment(assignment) => {
-            eval_assignment(assignment, global_scope)?;
-            Ok(Atomic::Null)
-        }
+        Statement::VariableAssignment(assignment) => eval_assignment(assignment, global_scope),
         Statement::Conditional(c) => eval_conditional(c, global_scope),
         Statement::Expression(e) => {
             let token = eval_expression(e, global_scope)?;
@@ -88,21 +459,353 @@ fn eval_statement(
         }
         Statement::Print(expression) => {
             let result = eval_expression(expression, global_scope)?;
-            // println!("{:?}", result);
-            println!("{}", result);
+            if !matches!(result, Atomic::Interrupt) {
+                global_scope.console().print(&result.to_string());
+            }
             Ok(result)
         }
         Statement::Loop(loop_statement) => eval_loop(loop_statement, global_scope),
+        Statement::Match(match_statement) => eval_match(match_statement, global_scope),
+        Statement::Try(try_statement) => eval_try(try_statement, global_scope),
+        // Wie bei `loss` darf es die Variable noch nicht geben, dann läuft das Modul gar nicht
+        Statement::Import(path) => {
+            let name = module_name(Path::new(path));
+            let slot = global_scope.names.intern(&name);
+            ensure_undefined(slot, &name, global_scope)?;
+            let module = eval_import(path, global_scope)?;
+            global_scope.replace(slot, Some(Atomic::Module(module)));
+            Ok(Atomic::Null)
+        }
+        Statement::Throw(expression) => {
+            let message = expression.as_string(global_scope)?;
+            Err(RuntimeError::new(ErrorKind::Thrown, message))
+        }
+        Statement::Struct(definition) => {
+            define(
+                &definition.name,
+                Atomic::Struct(definition.clone()),
+                global_scope,
+            )?;
+            Ok(Atomic::Null)
+        }
+        // Jede Variante wird eine Variable, Varianten ohne Felder direkt ein Wert
+        Statement::Enum(definition) => {
+            for variant in &definition.variants {
+                let value = if variant.fields.is_empty() {
+                    Atomic::VariantValue(Rc::new(VariantValue {
+                        variant: variant.clone(),
+                        values: vec![],
+                    }))
+                } else {
+                    Atomic::Variant(variant.clone())
+                };
+                define(&variant.name, value, global_scope)?;
+            }
+            Ok(Atomic::Null)
+        }
+        Statement::FieldAssignment(assignment) => {
+            eval_field_assignment(assignment, global_scope)?;
+            Ok(Atomic::Null)
+        }
+        // Tests laufen nur mit `ubpp test`, siehe `testing::run_tests`
+        Statement::Test(_) => Ok(Atomic::Null),
+        Statement::Expect(expect) => {
+            eval_expect(expect, global_scope)?;
+            Ok(Atomic::Null)
+        }
+        Statement::WriteFile(write_file) => {
+            let content = write_file.content.as_string(global_scope)?;
+            let path = write_file.path.as_string(global_scope)?;
+            builtins::write(global_scope, &path, &content, write_file.append)?;
+            Ok(Atomic::Null)
+        }
     }
 }
 
-fn eval_loop(
-    loop_statement: &Loop,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
+fn eval_import(path: &str, global_scope: &mut Scope) -> Result<Module, RuntimeError> {
+    let import_error = |message: String| RuntimeError::new(ErrorKind::Import, message);
+    // Wie `lies datei` braucht `bruuch` eine Freigabe, und zwar bevor sich zeigt, ob es die Datei
+    // gibt
+    let path = global_scope
+        .permissions
+        .check(&global_scope.relative_path(path), false)?
+        .canonicalize()
+        .map_err(|e| import_error(format!("Cannot find `{}` ({})", path, e)))?;
+    match global_scope.modules.get(&path) {
+        Some(ModuleState::Loaded(module)) => return Ok(module.clone()),
+        Some(ModuleState::Loading) => {
+            return Err(import_error(format!(
+                "Import cycle: `{}` is already being imported",
+                path.display()
+            )))
+        }
+        None => {}
+    }
+
+    let source = std::fs::read_to_string(&path)
+        .map_err(|e| import_error(format!("Cannot read `{}` ({})", path.display(), e)))?;
+    let dialect = match Dialect::from_header(&source) {
+        Some(dialect) => dialect.map_err(import_error)?,
+        None => Dialect::default(),
+    };
+    // Ohne die Zeile selbst, damit der Inhalt der Datei nicht in der Fehlermeldung landet
+    let nesting_limit = global_scope.nesting_limit.unwrap_or(MAX_NESTING);
+    let mut tokens = dialect.parse(&source, nesting_limit).map_err(|e| {
+        let (line, column) = match e.line_col {
+            LineColLocation::Pos(position) | LineColLocation::Span(position, _) => position,
+        };
+        import_error(format!(
+            "Cannot parse `{}` at line {}, column {}: {}",
+            path.display(),
+            line,
+            column,
+            e.variant.message()
+        ))
+    })?;
+
+    // Das Modul läuft in einem eigenen Scope, teilt sich aber den Cache und die Slots mit dem
+    // Importeur
+    global_scope
+        .modules
+        .insert(path.clone(), ModuleState::Loading);
+    let mut module_scope = Scope {
+        variables: vec![],
+        names: std::mem::take(&mut global_scope.names),
+        path: Some(path.clone()),
+        permissions: global_scope.permissions.clone(),
+        now: global_scope.now,
+        seed: global_scope.seed,
+        step_limit: global_scope.step_limit,
+        steps: global_scope.steps,
+        nesting_limit: global_scope.nesting_limit,
+        depth_limit: global_scope.depth_limit,
+        depth: global_scope.depth,
+        strict: global_scope.strict || has_strict_pragma(&source),
+        console: global_scope.console.take(),
+        rng: global_scope.rng.take(),
+        modules: std::mem::take(&mut global_scope.modules),
+    };
+    let result = resolve_tokens(&mut tokens, &mut module_scope)
+        .and_then(|()| eval_block(&tokens, &mut module_scope));
+    let variables = module_scope
+        .defined()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+    global_scope.names = module_scope.names;
+    global_scope.modules = module_scope.modules;
+    global_scope.console = module_scope.console;
+    global_scope.rng = module_scope.rng;
+    global_scope.steps = module_scope.steps;
+    if let Err(e) = result {
+        global_scope.modules.remove(&path);
+        return Err(e);
+    }
+
+    let module = Module {
+        name: module_name(&path),
+        variables: Rc::new(variables),
+    };
+    global_scope
+        .modules
+        .insert(path, ModuleState::Loaded(module.clone()));
+    Ok(module)
+}
+
+/// Bei einem Vergleich werden beide Seiten in der Fehlermeldung ausgegeben, bei
+/// `gliich isch wie` gilt die rechte Seite als erwarteter Wert
+fn eval_expect(expect: &Expect, global_scope: &mut Scope) -> Result<(), RuntimeError> {
+    let failure = |message: String| {
+        Err(RuntimeError::new(
+            ErrorKind::Expectation,
+            format!("line {}: {}", expect.line, message),
+        ))
+    };
+    let Expression::Comparison(comparison) = &expect.condition else {
+        let actual = eval_expression(&expect.condition, global_scope)?;
+        if global_scope.coerce_bool(actual.clone())? {
+            return Ok(());
+        }
+        return failure(format!("expected `true`, got `{}`", actual));
+    };
+    let (lhs, rhs, operator) = match comparison.as_ref() {
+        Comparison::Smaller(lhs, rhs) => (lhs, rhs, "isch kliiner als"),
+        Comparison::SmallerEquals(lhs, rhs) => (lhs, rhs, "isch kliiner oder gliich wie"),
+        Comparison::Equals(lhs, rhs) => (lhs, rhs, "gliich isch wie"),
+        Comparison::NotEquals(lhs, rhs) => (lhs, rhs, "ungliich isch wie"),
+        Comparison::Greater(lhs, rhs) => (lhs, rhs, "isch grösser als"),
+        Comparison::GreaterEquals(lhs, rhs) => (lhs, rhs, "isch grösser oder gliich wie"),
+    };
+    let actual = eval_expression(lhs, global_scope)?;
+    let expected = eval_expression(rhs, global_scope)?;
+    if comparison_holds(comparison, actual.clone(), expected.clone(), global_scope)? {
+        return Ok(());
+    }
+    match comparison.as_ref() {
+        Comparison::Equals(..) => failure(format!("expected `{}`, got `{}`", expected, actual)),
+        _ => failure(format!("expected `{}` {} `{}`", actual, operator, expected)),
+    }
+}
+
+fn eval_try(try_statement: &Try, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    let error = match eval_block(&try_statement.body, global_scope) {
+        Ok(result) => return Ok(result),
+        // Ist das Limit erreicht, würde auch der `fang` Block sofort abbrechen
+        Err(error) if error.kind == ErrorKind::Limit => return Err(error),
+        Err(error) => error,
+    };
+    // Der Fehler ist nur innerhalb vom `fang` Block sichtbar
+    let slot = try_statement.error_ident.slot;
+    let shadowed = global_scope.replace(slot, Some(Atomic::Error(error)));
+    let result = eval_block(&try_statement.catch_body, global_scope);
+    global_scope.replace(slot, shadowed);
+    result
+}
+
+/// Passt kein Arm, passiert nichts. Nur bei Varianten ist das ein Fehler, dort müssen alle
+/// Fälle behandelt werden.
+fn eval_match(match_statement: &Match, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    let value = eval_expression(&match_statement.value, global_scope)?;
+    for arm in &match_statement.arms {
+        if let Some(bindings) = matches_patterns(&value, &arm.patterns, global_scope)? {
+            return with_bindings(bindings, global_scope, |global_scope| {
+                eval_block(&arm.body, global_scope)
+            });
+        }
+    }
+    if let Atomic::VariantValue(_) = value {
+        return Err(RuntimeError::new(
+            ErrorKind::NoMatch,
+            format!("No arm matches `{}`", value),
+        ));
+    }
+    Ok(Atomic::Null)
+}
+
+fn eval_match_expression(
+    match_expression: &MatchExpression,
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    let value = eval_expression(&match_expression.value, global_scope)?;
+    for arm in &match_expression.arms {
+        if let Some(bindings) = matches_patterns(&value, &arm.patterns, global_scope)? {
+            return with_bindings(bindings, global_scope, |global_scope| {
+                eval_branch(&arm.body, &arm.body_expression, global_scope)
+            });
+        }
+    }
+    Err(RuntimeError::new(
+        ErrorKind::NoMatch,
+        format!("No arm matches `{}`", value),
+    ))
+}
+
+/// Gibt beim ersten passenden Muster die Slots und Werte der Variablen zurück, die das Muster
+/// bindet
+fn matches_patterns(
+    atomic: &Atomic,
+    patterns: &[Pattern],
+    global_scope: &mut Scope,
+) -> Result<Option<Vec<(usize, Atomic)>>, RuntimeError> {
+    let value = Expression::Atomic(atomic.clone());
+    for pattern in patterns {
+        let is_match = match pattern {
+            Pattern::Literal(literal) => {
+                let equals = Comparison::Equals(value.clone(), Expression::Atomic(literal.clone()));
+                eval_comparison(&equals, global_scope)?
+            }
+            Pattern::Range(from, to) => {
+                let from =
+                    Comparison::GreaterEquals(value.clone(), Expression::Atomic(from.clone()));
+                let to = Comparison::SmallerEquals(value.clone(), Expression::Atomic(to.clone()));
+                eval_logic_op(
+                    &LogicOp::And(
+                        Expression::Comparison(Box::new(from)),
+                        Expression::Comparison(Box::new(to)),
+                    ),
+                    global_scope,
+                )?
+            }
+            Pattern::Variant { name, bindings } => {
+                let bindings = match_variant(atomic, name, bindings, global_scope)?;
+                if bindings.is_some() {
+                    return Ok(bindings);
+                }
+                continue;
+            }
+            Pattern::Default => return Ok(Some(vec![])),
+        };
+        if matches!(is_match, Atomic::Bool(true)) {
+            return Ok(Some(vec![]));
+        }
+    }
+    Ok(None)
+}
+
+/// `isch Kreis(r)` passt auf Werte der Variante `Kreis` und bindet ihr Feld an `r`
+fn match_variant(
+    value: &Atomic,
+    name: &Variable,
+    bindings: &[Variable],
+    global_scope: &mut Scope,
+) -> Result<Option<Vec<(usize, Atomic)>>, RuntimeError> {
+    let variant: Rc<Variant> = match lookup(name, global_scope)? {
+        Atomic::Variant(variant) => variant,
+        Atomic::VariantValue(value) => value.variant.clone(),
+        other => {
+            return Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("`{}` is not a variant", other),
+            ))
+        }
+    };
+    if !bindings.is_empty() && bindings.len() != variant.fields.len() {
+        return Err(RuntimeError::new(
+            ErrorKind::Argument,
+            format!(
+                "`{}` has {} field(s), the pattern binds {}",
+                variant.name,
+                variant.fields.len(),
+                bindings.len()
+            ),
+        ));
+    }
+    let Atomic::VariantValue(value) = value else {
+        return Ok(None);
+    };
+    if !Rc::ptr_eq(&value.variant, &variant) {
+        return Ok(None);
+    }
+    Ok(Some(
+        bindings
+            .iter()
+            .map(|binding| binding.slot)
+            .zip(value.values.iter().cloned())
+            .collect(),
+    ))
+}
+
+/// Führt `f` mit zusätzlichen Variablen aus. Danach gelten wieder die vorherigen Werte.
+fn with_bindings<T>(
+    bindings: Vec<(usize, Atomic)>,
+    global_scope: &mut Scope,
+    f: impl FnOnce(&mut Scope) -> T,
+) -> T {
+    let shadowed = bindings
+        .into_iter()
+        .map(|(slot, value)| (slot, global_scope.replace(slot, Some(value))))
+        .collect::<Vec<_>>();
+    let result = f(global_scope);
+    for (slot, previous) in shadowed {
+        global_scope.replace(slot, previous);
+    }
+    result
+}
+
+fn eval_loop(loop_statement: &Loop, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
     let mut condition = loop_statement.condition.as_bool(global_scope)?;
     while condition {
-        let token = eval_tokens(&loop_statement.body, global_scope)?;
+        global_scope.step()?;
+        let token = eval_block(&loop_statement.body, global_scope)?;
         if matches!(token, Atomic::Interrupt) {
             return Ok(Atomic::Null);
         }
@@ -113,94 +816,371 @@ fn eval_loop(
 
 fn eval_conditional(
     conditional: &crate::step1::Conditional,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
     let condition = conditional.condition.as_bool(global_scope)?;
     if condition {
-        let token = eval_tokens(&conditional.body, global_scope)?;
-        Ok(token)
-    } else if let Some(tokens) = conditional.else_body.as_ref() {
-        let token = eval_tokens(tokens, global_scope)?;
+        let token = eval_block(&conditional.body, global_scope)?;
+        return Ok(token);
+    }
+    for branch in &conditional.else_if {
+        if branch.condition.as_bool(global_scope)? {
+            return eval_block(&branch.body, global_scope);
+        }
+    }
+    if let Some(tokens) = conditional.else_body.as_ref() {
+        let token = eval_block(tokens, global_scope)?;
         Ok(token)
     } else {
         Ok(Atomic::Null)
     }
 }
 
+/// Ein Modul heisst wie seine Datei ohne Endung
+pub(crate) fn module_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Definiert eine neue Variable, die es noch nicht geben darf
+fn define(ident: &str, value: Atomic, global_scope: &mut Scope) -> Result<(), RuntimeError> {
+    let slot = global_scope.names.intern(ident);
+    ensure_undefined(slot, ident, global_scope)?;
+    global_scope.replace(slot, Some(value));
+    Ok(())
+}
+
+/// `loss`, `bruuch` und Definitionen dürfen keine bestehende Variable überschreiben
+fn ensure_undefined(slot: usize, ident: &str, global_scope: &Scope) -> Result<(), RuntimeError> {
+    if global_scope.is_defined(slot) {
+        return Err(RuntimeError::new(
+            ErrorKind::AlreadyDefined,
+            format!("`{}` already defined", ident),
+        ));
+    }
+    Ok(())
+}
+
+/// Records sind Werte: die Zuweisung ändert nur die Kopie in dieser Variable
+fn eval_field_assignment(
+    assignment: &FieldAssignment,
+    global_scope: &mut Scope,
+) -> Result<(), RuntimeError> {
+    let value = eval_expression(&assignment.value, global_scope)?;
+    let target = lookup(&assignment.ident, global_scope)?;
+    let field = match &target {
+        Atomic::Record(record) => record.field_index(&assignment.field),
+        _ => None,
+    };
+    let (Atomic::Record(mut record), Some(field)) = (target.clone(), field) else {
+        return Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("`{}` has no field `{}`", target, assignment.field),
+        ));
+    };
+    Rc::make_mut(&mut record).values[field] = value;
+    global_scope.replace(assignment.ident.slot, Some(Atomic::Record(record)));
+    Ok(())
+}
+
+/// Ein `stop;` im Wert, z.B. in einem `falls` Ausdruck, wird weitergegeben, ohne die Variable zu
+/// ändern
 fn eval_assignment(
     assignment: &crate::step1::VariableAssignment,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<(), String> {
-    if assignment.new_definition && global_scope.contains_key(&assignment.ident) {
-        return Err(format!("`{}` already defined", assignment.ident));
-    } else {
-        let result = eval_expression(&assignment.value, global_scope)?;
-        global_scope.insert(assignment.ident.to_string(), Expression::Atomic(result));
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    let slot = assignment.ident.slot;
+    if assignment.new_definition {
+        ensure_undefined(slot, &assignment.ident.name, global_scope)?;
     }
-    Ok(())
+    let result = eval_expression(&assignment.value, global_scope)?;
+    if matches!(result, Atomic::Interrupt) {
+        return Ok(result);
+    }
+    global_scope.replace(slot, Some(result));
+    Ok(Atomic::Null)
 }
 
-fn eval_expression(
-    e: &Expression,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
-    match e {
+/// Der Wert einer Variable. Ohne Wert gilt die eingebaute Funktion mit demselben Namen.
+fn lookup(variable: &Variable, global_scope: &Scope) -> Result<Atomic, RuntimeError> {
+    if let Some(value) = global_scope.value(variable.slot) {
+        return Ok(value.clone());
+    }
+    match global_scope.names.builtin(variable.slot) {
+        Some(builtin) => Ok(builtin.clone()),
+        None => Err(RuntimeError::new(
+            ErrorKind::Undefined,
+            format!("`{}` not defined!", variable.name),
+        )),
+    }
+}
+
+fn eval_expression(e: &Expression, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    global_scope.nested(|global_scope| match e {
         Expression::Atomic(atomic) => Ok(atomic.to_owned()),
-        Expression::Ident(ident) => {
-            let ident_expression = if let Some(expr) = global_scope.get(ident) {
-                expr.clone()
-            } else {
-                return Err(format!("[ERROR] `{}` not defined!", ident));
-            };
-            eval_expression(&ident_expression, global_scope)
-        }
+        Expression::Ident(variable) => lookup(variable, global_scope),
         Expression::Input(expression) => {
-            let mut s = String::new();
-            println!("{}", expression.as_string(global_scope)?);
-            stdin().read_line(&mut s).map_err(|e| format!("{:?}", e))?;
+            let prompt = expression.as_string(global_scope)?;
+            let s = global_scope
+                .console()
+                .input(&prompt)
+                .map_err(|e| RuntimeError::new(ErrorKind::Input, format!("{:?}", e)))?;
             Ok(Atomic::String(s))
         }
         Expression::LogicOp(logic_operation) => eval_logic_op(logic_operation, global_scope),
+        Expression::UnaryOp(unary_operation) => eval_unary_op(unary_operation, global_scope),
         Expression::Comparison(comparison) => eval_comparison(comparison, global_scope),
         Expression::BinaryOp(num_op) => eval_binary_op(num_op, global_scope),
         Expression::Conditional(conditional) => {
             eval_conditional_expression(conditional, global_scope)
         }
+        Expression::Match(match_expression) => {
+            eval_match_expression(match_expression, global_scope)
+        }
+        Expression::FieldAccess(field_access) => eval_field_access(field_access, global_scope),
+        Expression::Call(call) => eval_call(call, global_scope),
+        Expression::Function(definition) => Ok(Atomic::Function(Rc::new(Closure {
+            definition: definition.clone(),
+            captured: definition
+                .captures
+                .iter()
+                .filter_map(|&slot| Some((slot, global_scope.value(slot)?.clone())))
+                .collect(),
+        }))),
+        Expression::Random(range) => {
+            let from = eval_expression(&range.from, global_scope)?;
+            let to = eval_expression(&range.to, global_scope)?;
+            builtins::random_number(global_scope, from, to)
+        }
+        Expression::ReadFile(path) => {
+            let path = path.as_string(global_scope)?;
+            Ok(Atomic::String(builtins::read(global_scope, &path)?))
+        }
         Expression::Cast(cast) => match cast.as_ref() {
             crate::step1::Cast::String(expr) => {
-                let result = expr.as_string(global_scope)?;
+                let result = eval_expression(expr, global_scope)?.into_string()?;
                 Ok(Atomic::String(result))
             }
-            crate::step1::Cast::Int(expr) => {
-                let result = expr.as_num(global_scope)?;
+            crate::step1::Cast::Number(expr) => eval_expression(expr, global_scope)?.into_numeric(),
+            crate::step1::Cast::Integer(expr) => {
+                let result = eval_expression(expr, global_scope)?.into_integer()?;
+                Ok(Atomic::Integer(result))
+            }
+            crate::step1::Cast::Float(expr) => {
+                let result = eval_expression(expr, global_scope)?.into_num()?;
                 Ok(Atomic::Number(result))
             }
             crate::step1::Cast::Bool(expr) => {
-                let result = expr.as_bool(global_scope)?;
+                let result = eval_expression(expr, global_scope)?.into_bool()?;
                 Ok(Atomic::Bool(result))
             }
+            crate::step1::Cast::Json(expr) => {
+                let result = eval_expression(expr, global_scope)?;
+                Ok(Atomic::String(builtins::to_json(&result)?))
+            }
+            crate::step1::Cast::FromJson(expr) => {
+                let text = expr.as_string(global_scope)?;
+                builtins::parse_json(&text)
+            }
         },
+    })
+}
+
+fn eval_call(call: &Call, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    let function = eval_expression(&call.function, global_scope)?;
+    let arguments = call
+        .arguments
+        .iter()
+        .map(|argument| eval_expression(argument, global_scope))
+        .collect::<Result<Vec<_>, _>>()?;
+    match function {
+        Atomic::Builtin(builtin) => (builtin.function)(global_scope, arguments),
+        Atomic::Function(closure) => call_closure(closure, arguments, global_scope),
+        Atomic::Struct(definition) => construct(definition, arguments),
+        Atomic::Variant(variant) => construct_variant(variant, arguments),
+        other => Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("`{}` is not a function", other),
+        )),
+    }
+}
+
+/// `Person("Anna", 12)`, die Argumente sind die Felder in der Reihenfolge der Definition
+fn construct(definition: Rc<Struct>, arguments: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    if arguments.len() != definition.fields.len() {
+        return Err(RuntimeError::new(
+            ErrorKind::Argument,
+            format!(
+                "`{}` expects {} argument(s), got {}",
+                definition.name,
+                definition.fields.len(),
+                arguments.len()
+            ),
+        ));
+    }
+    Ok(Atomic::Record(Rc::new(Record {
+        definition,
+        values: arguments,
+    })))
+}
+
+/// `Kreis(2)`, wie bei [`construct`]
+fn construct_variant(variant: Rc<Variant>, arguments: Vec<Atomic>) -> Result<Atomic, RuntimeError> {
+    if arguments.len() != variant.fields.len() {
+        return Err(RuntimeError::new(
+            ErrorKind::Argument,
+            format!(
+                "`{}` expects {} argument(s), got {}",
+                variant.name,
+                variant.fields.len(),
+                arguments.len()
+            ),
+        ));
+    }
+    Ok(Atomic::VariantValue(Rc::new(VariantValue {
+        variant,
+        values: arguments,
+    })))
+}
+
+/// Die Funktion sieht nur ihre eingefangenen Variablen, sich selbst und die Parameter.
+/// Zuweisungen im Körper bleiben lokal.
+fn call_closure(
+    closure: Rc<Closure>,
+    arguments: Vec<Atomic>,
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    let definition = &closure.definition;
+    if arguments.len() != definition.parameters.len() {
+        return Err(RuntimeError::new(
+            ErrorKind::Argument,
+            format!(
+                "`{}` expects {} argument(s), got {}",
+                Atomic::Function(closure.clone()),
+                definition.parameters.len(),
+                arguments.len()
+            ),
+        ));
+    }
+    global_scope.step()?;
+    let outer = std::mem::take(&mut global_scope.variables);
+    for (slot, value) in &closure.captured {
+        global_scope.replace(*slot, Some(value.clone()));
+    }
+    if let Some(slot) = definition.own_slot {
+        global_scope.replace(slot, Some(Atomic::Function(closure.clone())));
+    }
+    for (parameter, argument) in definition.parameters.iter().zip(arguments) {
+        global_scope.replace(parameter.slot, Some(argument));
+    }
+    let result = eval_function_body(definition, global_scope);
+    global_scope.variables = outer;
+    result
+}
+
+/// `stop;` beendet die Funktion vorzeitig mit `null`
+fn eval_function_body(
+    definition: &FunctionDefinition,
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    if let Atomic::Interrupt = eval_block(&definition.body, global_scope)? {
+        return Ok(Atomic::Null);
+    }
+    match &definition.body_expression {
+        Some(body_expression) => eval_expression(body_expression, global_scope),
+        None => Ok(Atomic::Null),
+    }
+}
+
+fn eval_field_access(
+    field_access: &FieldAccess,
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    let value = eval_expression(&field_access.value, global_scope)?;
+    if let Atomic::Record(record) = &value {
+        if let Some(index) = record.field_index(&field_access.field) {
+            return Ok(record.values[index].clone());
+        }
+    }
+    if let Atomic::VariantValue(variant_value) = &value {
+        let fields = &variant_value.variant.fields;
+        if let Some(index) = fields.iter().position(|f| *f == field_access.field) {
+            return Ok(variant_value.values[index].clone());
+        }
+    }
+    match (&value, field_access.field.as_str()) {
+        (Atomic::Error(e), "art") => Ok(Atomic::String(e.kind.to_string())),
+        (Atomic::Error(e), "meldig") => Ok(Atomic::String(e.message.clone())),
+        (Atomic::Map(map), field) if map.contains_key(field) => Ok(map[field].clone()),
+        (Atomic::Module(module), field) if module.variables.contains_key(field) => {
+            Ok(module.variables[field].clone())
+        }
+        _ => Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("`{}` has no field `{}`", value, field_access.field),
+        )),
     }
 }
 
 fn eval_conditional_expression(
     conditional: &ConditionalExpression,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
     let condition = conditional.condition.as_bool(global_scope)?;
     if condition {
-        let _ = eval_tokens(&conditional.body, global_scope);
-        eval_expression(&conditional.body_expression, global_scope)
-    } else {
-        let _ = eval_tokens(&conditional.else_body, global_scope);
-        eval_expression(&conditional.else_body_expression, global_scope)
+        return eval_branch(
+            &conditional.body,
+            &conditional.body_expression,
+            global_scope,
+        );
+    }
+    for branch in &conditional.else_if {
+        if branch.condition.as_bool(global_scope)? {
+            return eval_branch(&branch.body, &branch.body_expression, global_scope);
+        }
+    }
+    eval_branch(
+        &conditional.else_body,
+        &conditional.else_body_expression,
+        global_scope,
+    )
+}
+
+/// Wertet den Block eines Zweigs und dann den Ausdruck an seinem Ende aus. Ein Fehler im Block
+/// bricht den ganzen Ausdruck ab, und `stop;` wird wie aus jedem Block weitergegeben.
+fn eval_branch(
+    body: &[Token],
+    body_expression: &Expression,
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    if let Atomic::Interrupt = eval_block(body, global_scope)? {
+        return Ok(Atomic::Interrupt);
+    }
+    eval_expression(body_expression, global_scope)
+}
+
+fn eval_unary_op(
+    unary_operation: &UnaryOp,
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    match unary_operation {
+        UnaryOp::Not(operand) => Ok(Atomic::Bool(!operand.as_bool(global_scope)?)),
+        UnaryOp::Negate(operand) => {
+            let operand = eval_expression(operand, global_scope)?;
+            match global_scope.coerce_numeric(operand)? {
+                Atomic::Integer(i) => Ok(Atomic::Integer(-i)),
+                Atomic::Number(n) => Ok(Atomic::Number(-n)),
+                other => Err(not_a_number(other)),
+            }
+        }
     }
 }
 
 fn eval_logic_op(
     logic_operation: &LogicOp,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
     match logic_operation {
         crate::step1::LogicOp::And(lhs, rhs) => {
             let lhs = lhs.as_bool(global_scope)?;
@@ -217,102 +1197,246 @@ fn eval_logic_op(
 
 fn eval_comparison(
     comparison: &Comparison,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
-    match comparison {
-        crate::step1::Comparison::Smaller(lhs, rhs) => {
-            if let (Ok(l), Ok(r)) = (lhs.as_num(global_scope), rhs.as_num(global_scope)) {
-                Ok(Atomic::Bool(l < r))
-            } else if let (Ok(l), Ok(r)) =
-                (lhs.as_string(global_scope), rhs.as_string(global_scope))
-            {
-                Ok(Atomic::Bool(l < r))
-            } else {
-                Err(format!("Invalid operands to comparison ({:?})", comparison))
-            }
+    global_scope: &mut Scope,
+) -> Result<Atomic, RuntimeError> {
+    let (lhs, rhs) = match comparison {
+        Comparison::Smaller(lhs, rhs)
+        | Comparison::SmallerEquals(lhs, rhs)
+        | Comparison::Equals(lhs, rhs)
+        | Comparison::NotEquals(lhs, rhs)
+        | Comparison::Greater(lhs, rhs)
+        | Comparison::GreaterEquals(lhs, rhs) => (lhs, rhs),
+    };
+    let lhs = eval_expression(lhs, global_scope)?;
+    let rhs = eval_expression(rhs, global_scope)?;
+    Ok(Atomic::Bool(comparison_holds(
+        comparison,
+        lhs,
+        rhs,
+        global_scope,
+    )?))
+}
+
+fn comparison_holds(
+    comparison: &Comparison,
+    lhs: Atomic,
+    rhs: Atomic,
+    global_scope: &Scope,
+) -> Result<bool, RuntimeError> {
+    if !matches!(
+        comparison,
+        Comparison::Equals(..) | Comparison::NotEquals(..)
+    ) {
+        ensure_ordered(&lhs, &rhs)?;
+    }
+    let ordering = if global_scope.strict {
+        compare_strict(lhs, rhs)?
+    } else {
+        compare(lhs, rhs)?
+    };
+    Ok(match comparison {
+        Comparison::Smaller(..) => matches!(ordering, Some(Ordering::Less)),
+        Comparison::SmallerEquals(..) => {
+            matches!(ordering, Some(Ordering::Less | Ordering::Equal))
         }
-        crate::step1::Comparison::SmallerEquals(lhs, rhs) => {
-            if let (Ok(l), Ok(r)) = (lhs.as_num(global_scope), rhs.as_num(global_scope)) {
-                Ok(Atomic::Bool(l <= r))
-            } else if let (Ok(l), Ok(r)) =
-                (lhs.as_string(global_scope), rhs.as_string(global_scope))
-            {
-                Ok(Atomic::Bool(l <= r))
-            } else {
-                Err("Invalid operands to comparison".to_string())
-            }
+        Comparison::Equals(..) => matches!(ordering, Some(Ordering::Equal)),
+        Comparison::NotEquals(..) => !matches!(ordering, Some(Ordering::Equal)),
+        Comparison::Greater(..) => matches!(ordering, Some(Ordering::Greater)),
+        Comparison::GreaterEquals(..) => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
         }
-        crate::step1::Comparison::Equals(lhs, rhs) => {
-            if let (Ok(l), Ok(r)) = (lhs.as_num(global_scope), rhs.as_num(global_scope)) {
-                Ok(Atomic::Bool(l == r))
-            } else if let (Ok(l), Ok(r)) =
-                (lhs.as_string(global_scope), rhs.as_string(global_scope))
-            {
-                Ok(Atomic::Bool(l == r))
-            } else {
-                Err("Invalid operands to comparison".to_string())
-            }
+    })
+}
+
+/// Records, Varianten, Listen und Maps sind nur gleich oder ungleich, `<` usw. ist ein Fehler
+fn ensure_ordered(lhs: &Atomic, rhs: &Atomic) -> Result<(), RuntimeError> {
+    for value in [lhs, rhs] {
+        if matches!(
+            value,
+            Atomic::Record(_) | Atomic::VariantValue(_) | Atomic::List(_) | Atomic::Map(_)
+        ) {
+            return Err(RuntimeError::new(
+                ErrorKind::Type,
+                format!("Cannot compare `{}` with `{}`", lhs, rhs),
+            ));
         }
-        crate::step1::Comparison::Greater(lhs, rhs) => {
-            if let (Ok(l), Ok(r)) = (lhs.as_num(global_scope), rhs.as_num(global_scope)) {
-                Ok(Atomic::Bool(l > r))
-            } else if let (Ok(l), Ok(r)) =
-                (lhs.as_string(global_scope), rhs.as_string(global_scope))
-            {
-                Ok(Atomic::Bool(l > r))
-            } else {
-                Err("Invalid operands to comparison".to_string())
-            }
+    }
+    Ok(())
+}
+
+/// Vergleicht zwei Werte zuerst als Zahlen und sonst als Zeichenketten. Zusammengesetzte Werte
+/// sind nur gleich oder ungleich, und was sich nicht vergleichen lässt, ist ein Fehler.
+pub(crate) fn compare(lhs: Atomic, rhs: Atomic) -> Result<Option<Ordering>, RuntimeError> {
+    if let (Atomic::Date(l), Atomic::Date(r)) = (&lhs, &rhs) {
+        return Ok(Some(l.cmp(r)));
+    }
+    let is_compound = |value: &Atomic| {
+        matches!(
+            value,
+            Atomic::Record(_) | Atomic::VariantValue(_) | Atomic::List(_) | Atomic::Map(_)
+        )
+    };
+    if is_compound(&lhs) || is_compound(&rhs) {
+        return Ok(compound_equal(&lhs, &rhs)?.then_some(Ordering::Equal));
+    }
+    let is_scalar = |value: &Atomic| {
+        matches!(
+            value,
+            Atomic::String(_)
+                | Atomic::Number(_)
+                | Atomic::Integer(_)
+                | Atomic::Bool(_)
+                | Atomic::Null
+        )
+    };
+    if !is_scalar(&lhs) || !is_scalar(&rhs) {
+        return Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("Cannot compare `{}` with `{}`", lhs, rhs),
+        ));
+    }
+    if let (Ok(l), Ok(r)) = (lhs.clone().into_numeric(), rhs.clone().into_numeric()) {
+        return Ok(match Operands::new(l, r)? {
+            Operands::Integer(l, r) => Some(l.cmp(&r)),
+            Operands::Float(l, r) => l.partial_cmp(&r),
+        });
+    }
+    Ok(Some(lhs.into_string()?.cmp(&rhs.into_string()?)))
+}
+
+/// Im strikten Modus haben beide Seiten denselben Typ, und Zeichenketten werden nie als Zahlen
+/// verglichen
+fn compare_strict(lhs: Atomic, rhs: Atomic) -> Result<Option<Ordering>, RuntimeError> {
+    match (&lhs, &rhs) {
+        (Atomic::String(l), Atomic::String(r)) => Ok(Some(l.cmp(r))),
+        (Atomic::Integer(_) | Atomic::Number(_), Atomic::Integer(_) | Atomic::Number(_)) => {
+            compare(lhs, rhs)
         }
-        crate::step1::Comparison::GreaterEquals(lhs, rhs) => {
-            if let (Ok(l), Ok(r)) = (lhs.as_num(global_scope), rhs.as_num(global_scope)) {
-                Ok(Atomic::Bool(l >= r))
-            } else if let (Ok(l), Ok(r)) =
-                (lhs.as_string(global_scope), rhs.as_string(global_scope))
-            {
-                Ok(Atomic::Bool(l >= r))
-            } else {
-                Err("Invalid operands to comparison".to_string())
+        (l, r) if std::mem::discriminant(l) == std::mem::discriminant(r) => compare(lhs, rhs),
+        _ => Err(RuntimeError::new(
+            ErrorKind::Type,
+            format!("Cannot compare `{}` with `{}` in strict mode", lhs, rhs),
+        )),
+    }
+}
+
+/// Records sind gleich, wenn sie von derselben Struktur sind und alle Felder gleich sind, Werte
+/// einer Variante ebenso. Listen und Maps sind gleich, wenn alle Elemente bzw. Einträge gleich
+/// sind. Eine Reihenfolge gibt es nicht.
+fn compound_equal(lhs: &Atomic, rhs: &Atomic) -> Result<bool, RuntimeError> {
+    let (l, r) = match (lhs, rhs) {
+        (Atomic::Record(l), Atomic::Record(r)) if Rc::ptr_eq(&l.definition, &r.definition) => {
+            (&l.values, &r.values)
+        }
+        (Atomic::VariantValue(l), Atomic::VariantValue(r))
+            if Rc::ptr_eq(&l.variant, &r.variant) =>
+        {
+            (&l.values, &r.values)
+        }
+        (Atomic::List(l), Atomic::List(r)) if l.len() == r.len() => (l.as_ref(), r.as_ref()),
+        (Atomic::Map(l), Atomic::Map(r)) if l.len() == r.len() => {
+            for ((l_key, l), (r_key, r)) in l.iter().zip(r.iter()) {
+                if l_key != r_key || compare(l.clone(), r.clone())? != Some(Ordering::Equal) {
+                    return Ok(false);
+                }
             }
+            return Ok(true);
+        }
+        _ => return Ok(false),
+    };
+    for (l, r) in l.iter().zip(r) {
+        if compare(l.clone(), r.clone())? != Some(Ordering::Equal) {
+            return Ok(false);
         }
     }
+    Ok(true)
 }
 
-fn eval_binary_op(
-    num_op: &BinaryOp,
-    global_scope: &mut HashMap<String, Expression>,
-) -> Result<Atomic, String> {
+fn eval_binary_op(num_op: &BinaryOp, global_scope: &mut Scope) -> Result<Atomic, RuntimeError> {
+    let missing_operator = || RuntimeError::new(ErrorKind::Type, "Missing operator");
+    let (left, right) = match num_op {
+        BinaryOp::Plus { left, right }
+        | BinaryOp::Minus { left, right }
+        | BinaryOp::Mul { left, right }
+        | BinaryOp::Div { left, right }
+        | BinaryOp::Mod { left, right }
+        | BinaryOp::Pow { left, right } => (left, right),
+        BinaryOp::None => return Err(missing_operator()),
+    };
+    let left = eval_expression(left, global_scope)?;
+    let right = eval_expression(right, global_scope)?;
+    // Zwei Zeichenketten werden immer zusammengehängt, auch wenn sie wie Zahlen aussehen
+    if let (BinaryOp::Plus { .. }, Atomic::String(l), Atomic::String(r)) = (num_op, &left, &right) {
+        return Ok(Atomic::String(format!("{}{}", l, r)));
+    }
+    let operands = if global_scope.strict {
+        // Im strikten Modus werden nur Zahlen verrechnet
+        Operands::new(
+            global_scope.coerce_numeric(left)?,
+            global_scope.coerce_numeric(right)?,
+        )?
+    } else {
+        match (num_op, Operands::new(left.clone(), right.clone())) {
+            // Ist nur eine Seite eine Zeichenkette, wird zusammengehängt, wenn sie keine Zahl ist
+            (BinaryOp::Plus { .. }, Err(_))
+                if matches!(left, Atomic::String(_)) || matches!(right, Atomic::String(_)) =>
+            {
+                return Ok(Atomic::String(left.into_string()? + &right.into_string()?));
+            }
+            (_, operands) => operands?,
+        }
+    };
+    if matches!(num_op, BinaryOp::Div { .. } | BinaryOp::Mod { .. }) && operands.divisor_is_zero() {
+        return Err(RuntimeError::new(ErrorKind::Arithmetic, "Division by zero"));
+    }
     match num_op {
-        crate::step1::BinaryOp::Plus { left, right } => {
-            let left = left.as_num(global_scope)?;
-            let right = right.as_num(global_scope)?;
-            Ok(Atomic::Number(left + right))
-        }
-        crate::step1::BinaryOp::Minus { left, right } => {
-            let left = left.as_num(global_scope)?;
-            let right = right.as_num(global_scope)?;
-            Ok(Atomic::Number(left - right))
-        }
-        crate::step1::BinaryOp::Mul { left, right } => {
-            let left = left.as_num(global_scope)?;
-            let right = right.as_num(global_scope)?;
-            Ok(Atomic::Number(left * right))
-        }
-        crate::step1::BinaryOp::Div { left, right } => {
-            let left = left.as_num(global_scope)?;
-            let right = right.as_num(global_scope)?;
-            Ok(Atomic::Number(left / right))
-        }
-        crate::step1::BinaryOp::Mod { left, right } => {
-            let left = left.as_num(global_scope)?;
-            let right = right.as_num(global_scope)?;
-            Ok(Atomic::Number(left % right))
-        }
-        crate::step1::BinaryOp::Pow { left, right } => {
-            let left = left.as_num(global_scope)?;
-            let right = right.as_num(global_scope)?;
-            Ok(Atomic::Number(left.powf(right)))
-        }
-        crate::step1::BinaryOp::None => unreachable!(),
+        BinaryOp::Plus { .. } => Ok(match operands {
+            Operands::Integer(l, r) => Atomic::Integer(l + r),
+            Operands::Float(l, r) => Atomic::Number(l + r),
+        }),
+        BinaryOp::Minus { .. } => Ok(match operands {
+            Operands::Integer(l, r) => Atomic::Integer(l - r),
+            Operands::Float(l, r) => Atomic::Number(l - r),
+        }),
+        BinaryOp::Mul { .. } => Ok(match operands {
+            Operands::Integer(l, r) => Atomic::Integer(l * r),
+            Operands::Float(l, r) => Atomic::Number(l * r),
+        }),
+        BinaryOp::Div { .. } => Ok(match operands {
+            Operands::Integer(l, r) if (&l % &r).is_zero() => Atomic::Integer(l / r),
+            Operands::Integer(l, r) => {
+                Atomic::Number(l.to_f64().unwrap_or(f64::NAN) / r.to_f64().unwrap_or(f64::NAN))
+            }
+            Operands::Float(l, r) => Atomic::Number(l / r),
+        }),
+        BinaryOp::Mod { .. } => Ok(match operands {
+            Operands::Integer(l, r) => Atomic::Integer(l % r),
+            Operands::Float(l, r) => Atomic::Number(l % r),
+        }),
+        BinaryOp::Pow { .. } => match operands {
+            Operands::Integer(l, r) if !r.is_negative() => {
+                // Bei 0, 1 und -1 bleibt das Resultat klein, sonst hat es gut `bits * r` Bits
+                let exponent = r
+                    .to_u32()
+                    .filter(|exponent| {
+                        l.bits() <= 1
+                            || l.bits().saturating_mul(u64::from(*exponent)) <= MAX_POW_BITS
+                    })
+                    .ok_or_else(|| {
+                        RuntimeError::new(
+                            ErrorKind::Arithmetic,
+                            format!("Exponent `{}` too large", r),
+                        )
+                    })?;
+                Ok(Atomic::Integer(l.pow(exponent)))
+            }
+            Operands::Integer(l, r) => Ok(Atomic::Number(
+                l.to_f64()
+                    .unwrap_or(f64::NAN)
+                    .powf(r.to_f64().unwrap_or(f64::NAN)),
+            )),
+            Operands::Float(l, r) => Ok(Atomic::Number(l.powf(r))),
+        },
+        BinaryOp::None => Err(missing_operator()),
     }
 }
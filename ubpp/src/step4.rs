@@ -6,16 +6,120 @@
 
 use std::fmt::Display;
 
-use crate::step1::Atomic;
+use chrono::NaiveTime;
+
+use crate::step1::{Atomic, ErrorKind, RuntimeError};
 
 impl Display for Atomic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Atomic::String(s) => f.write_str(s),
+            // Kommazahlen werden immer mit Dezimalstelle ausgegeben, damit sie sich von
+            // Ganzzahlen unterscheiden
+            Atomic::Number(n) if n.is_finite() && n.fract() == 0.0 => write!(f, "{:.1}", n),
             Atomic::Number(n) => f.write_str(&n.to_string()),
+            Atomic::Integer(i) => f.write_str(&i.to_string()),
             Atomic::Bool(b) => f.write_str(&b.to_string()),
             Atomic::Null => f.write_str("null"),
+            Atomic::Error(e) => e.fmt(f),
+            Atomic::Module(module) => write!(f, "<modul {}>", module.name),
+            Atomic::Builtin(builtin) => write!(f, "<funktion {}>", builtin.name),
+            Atomic::Function(closure) => {
+                let parameters = closure
+                    .definition
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.name.as_ref())
+                    .collect::<Vec<_>>();
+                write!(f, "<funktion ({})>", parameters.join(", "))
+            }
+            Atomic::Struct(definition) => write!(f, "<struktur {}>", definition.name),
+            Atomic::Record(record) => {
+                write!(f, "{} {{ ", record.definition.name)?;
+                for (i, (field, value)) in record
+                    .definition
+                    .fields
+                    .iter()
+                    .zip(&record.values)
+                    .enumerate()
+                {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", field, value)?;
+                }
+                f.write_str(" }")
+            }
+            Atomic::Variant(variant) => {
+                write!(f, "<variante {}.{}>", variant.enum_name, variant.name)
+            }
+            Atomic::VariantValue(value) if value.values.is_empty() => {
+                f.write_str(&value.variant.name)
+            }
+            Atomic::VariantValue(value) => {
+                write!(f, "{}(", value.variant.name)?;
+                for (i, value) in value.values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    value.fmt(f)?;
+                }
+                f.write_str(")")
+            }
+            Atomic::List(list) => {
+                f.write_str("[")?;
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    value.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            // Ohne Uhrzeit wird nur das Datum ausgegeben
+            Atomic::Date(date) if date.time() == NaiveTime::MIN => {
+                write!(f, "{}", date.format("%d.%m.%Y"))
+            }
+            Atomic::Date(date) => write!(f, "{}", date.format("%d.%m.%Y %H:%M")),
+            Atomic::Map(map) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                f.write_str("}")
+            }
             Atomic::Interrupt => f.write_str("<< interrupt >>"),
         }
     }
-}
\ No newline at end of file
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Type => f.write_str("type"),
+            ErrorKind::Undefined => f.write_str("undefined"),
+            ErrorKind::AlreadyDefined => f.write_str("already defined"),
+            ErrorKind::Input => f.write_str("input"),
+            ErrorKind::NoMatch => f.write_str("no match"),
+            ErrorKind::Arithmetic => f.write_str("arithmetic"),
+            ErrorKind::Thrown => f.write_str("thrown"),
+            ErrorKind::Import => f.write_str("import"),
+            ErrorKind::Argument => f.write_str("argument"),
+            ErrorKind::Index => f.write_str("index"),
+            ErrorKind::Permission => f.write_str("permission"),
+            ErrorKind::File => f.write_str("file"),
+            ErrorKind::Json => f.write_str("json"),
+            ErrorKind::Expectation => f.write_str("expectation"),
+            ErrorKind::Limit => f.write_str("limit"),
+        }
+    }
+}
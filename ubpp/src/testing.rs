@@ -0,0 +1,84 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Testrunner für `ubpp test`. Ein Test ist ein `teste "name" { ... }` Block auf oberster
+//! Ebene einer Datei.
+
+use std::path::{Path, PathBuf};
+
+use crate::step1::{RuntimeError, Statement, Token};
+use crate::resolve::{resolve, Program};
+use crate::step3::{eval_block, eval_program, Scope};
+
+/// Resultat eines einzelnen `teste` Blocks
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub line: usize,
+    pub outcome: Result<(), RuntimeError>,
+}
+
+/// Sucht alle `.ubpp` Dateien, Verzeichnisse werden rekursiv durchsucht
+pub fn find_files(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        collect_files(path, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "ubpp")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Führt alle Tests aus. Jeder Test bekommt einen frischen Scope, in dem zuerst der restliche
+/// Code der Datei läuft, damit die Tests dessen Variablen sehen. Aufgelöst wird die Datei nur
+/// einmal, ist ein Name nirgends definiert, schlagen alle Tests fehl.
+pub fn run_tests(tokens: &[Token], mut new_scope: impl FnMut() -> Scope) -> Vec<TestResult> {
+    let program = resolve(tokens.to_vec(), &mut new_scope());
+    let tokens = program.as_ref().map_or(tokens, Program::tokens);
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Statement(Statement::Test(test)) => Some(test),
+            _ => None,
+        })
+        .map(|test| {
+            let outcome = match &program {
+                Ok(program) => {
+                    let mut scope = new_scope();
+                    eval_program(program, &mut scope)
+                        .and_then(|_| eval_block(&test.body, &mut scope))
+                        .map(|_| ())
+                }
+                Err(e) => Err(e.clone()),
+            };
+            TestResult {
+                name: test.name.clone(),
+                line: test.line,
+                outcome,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,250 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Erzeugt die TextMate-Grammatik für die VSCode-Erweiterung in `ubpp-syntax/` aus `ubpp.pest`,
+//! damit die Schlüsselwörter nicht von Hand nachgeführt werden müssen. Neu erzeugt wird sie mit
+//! `cargo run --bin textmate`.
+
+use std::collections::BTreeSet;
+
+use pest_meta::{
+    ast::{Expr, Rule, RuleType},
+    parser::{self, consume_rules},
+};
+use serde_json::{json, Value};
+
+/// Eine Gruppe im `repository` der TextMate-Grammatik mit den Regeln, deren Literale dazugehören
+struct Group {
+    name: &'static str,
+    scope: &'static str,
+    rules: &'static [&'static str],
+}
+
+/// Die Reihenfolge ist wichtig: Bei gleichem Anfang gewinnt die frühere Gruppe, darum kommt z.B.
+/// `isch gliich wie` vor `isch`.
+const GROUPS: &[Group] = &[
+    Group {
+        name: "operators",
+        scope: "keyword.operator",
+        rules: &[
+            "equals",
+            "not_equals",
+            "smaller_than",
+            "smaller_equals",
+            "greater_than",
+            "greater_equals",
+            "and",
+            "or",
+            "not_op",
+            "neg_op",
+            "plus",
+            "minus",
+            "mul",
+            "div",
+            "mod_op",
+            "pow",
+            "cast_operator",
+            "from_json",
+        ],
+    },
+    Group {
+        name: "known-functions",
+        scope: "entity.name.function",
+        rules: &[
+            "print",
+            "input",
+            "read_file_name",
+            "write_name",
+            "append_name",
+            "random_name",
+        ],
+    },
+    Group {
+        name: "keywords",
+        scope: "keyword.control.ubpp",
+        rules: &[
+            "if_name",
+            "else_name",
+            "while_name",
+            "do_name",
+            "break_keyword",
+            "match_name",
+            "default_pattern",
+            "try_name",
+            "catch_name",
+            "throw_name",
+            "import_name",
+            "test_name",
+            "expect_name",
+            "function_name",
+            "let_name",
+            "struct_name",
+            "enum_name",
+        ],
+    },
+    Group {
+        name: "equalsSign",
+        scope: "markup.quote",
+        rules: &["variable_statement", "field_assignment"],
+    },
+    Group {
+        name: "predefined-types",
+        scope: "entity.name.type",
+        rules: &["bool", "number", "string", "integer", "float", "json"],
+    },
+    Group {
+        name: "booleans",
+        scope: "constant.numeric",
+        rules: &["true_literal", "false_literal"],
+    },
+];
+
+/// Satzzeichen wie `;` am Ende einer Anweisung werden nie hervorgehoben
+const PUNCTUATION: &[&str] = &[";", ",", ":", "(", ")", "{", "}"];
+
+/// Übrige Wörter wie `de` oder `bis`, die direkt in einer Regel stehen, sind Schlüsselwörter
+const DEFAULT_GROUP: &str = "keywords";
+
+/// Erzeugt das JSON für `ubpp.tmLanguage.json` aus dem Inhalt von `ubpp.pest`
+pub fn textmate_grammar(grammar: &str) -> Result<String, String> {
+    let pairs = parser::parse(parser::Rule::grammar_rules, grammar).map_err(|e| e.to_string())?;
+    let rules = consume_rules(pairs).map_err(|errors| {
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
+
+    let mut claimed = BTreeSet::new();
+    let mut literals = vec![];
+    for group in GROUPS {
+        let mut group_literals = BTreeSet::new();
+        for name in group.rules {
+            let rule = rules
+                .iter()
+                .find(|rule| rule.name == *name)
+                .ok_or_else(|| format!("Unknown rule `{}` in group `{}`", name, group.name))?;
+            group_literals.extend(rule_literals(rule));
+        }
+        claimed.extend(group_literals.iter().cloned());
+        literals.push(group_literals);
+    }
+    let default = GROUPS
+        .iter()
+        .position(|group| group.name == DEFAULT_GROUP)
+        .ok_or_else(|| format!("Unknown group `{}`", DEFAULT_GROUP))?;
+    for rule in &rules {
+        if rule.ty == RuleType::Silent {
+            continue;
+        }
+        let words = rule_literals(rule)
+            .filter(|literal| is_word(literal) && !claimed.contains(literal))
+            .collect::<Vec<_>>();
+        literals[default].extend(words);
+    }
+
+    let mut patterns = vec![
+        json!({ "include": "#comments" }),
+        json!({ "include": "#strings" }),
+    ];
+    let mut repository = serde_json::Map::new();
+    for (group, literals) in GROUPS.iter().zip(literals) {
+        patterns.push(json!({ "include": format!("#{}", group.name) }));
+        let (words, symbols): (Vec<_>, Vec<_>) = literals.into_iter().partition(|l| is_word(l));
+        let mut group_patterns = vec![];
+        if !words.is_empty() {
+            group_patterns.push(json!({
+                "name": group.scope,
+                "match": format!("\\b({})\\b", alternatives(words)),
+            }));
+        }
+        if !symbols.is_empty() {
+            group_patterns.push(json!({
+                "name": group.scope,
+                "match": format!("({})", alternatives(symbols)),
+            }));
+        }
+        repository.insert(
+            group.name.to_string(),
+            json!({ "patterns": group_patterns }),
+        );
+    }
+    patterns.push(json!({ "include": "#numbers" }));
+    repository.insert(
+        "numbers".to_string(),
+        json!({
+            "patterns": [{ "name": "constant.numeric", "match": "-?\\d+(\\.\\d+)?" }]
+        }),
+    );
+    repository.insert(
+        "strings".to_string(),
+        json!({
+            "name": "string.quoted.double.ubpp",
+            "begin": "\"",
+            "end": "\"",
+            "patterns": [{
+                "name": "constant.character.escape.ubpp",
+                "match": "\\\\[\"\\\\]"
+            }, {
+                "name": "markup.italic",
+                "match": "\\d{4}-\\d{2}-\\d{2}(T\\d{2}:\\d{2}(:\\d{2}(\\.\\d+)?)?(([+-]\\d{2}(:\\d{2})?)|Z)?)?"
+            }]
+        }),
+    );
+    repository.insert(
+        "comments".to_string(),
+        json!({ "name": "comment.block", "begin": "/\\*", "end": "\\*/" }),
+    );
+
+    let grammar = json!({
+        "$schema": "https://raw.githubusercontent.com/martinring/tmlanguage/master/tmlanguage.json",
+        "name": "UB++",
+        "patterns": patterns,
+        "repository": Value::Object(repository),
+        "scopeName": "source.ubpp",
+    });
+    let mut json = serde_json::to_string_pretty(&grammar).map_err(|e| e.to_string())?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Alle Zeichenketten, die direkt in der Regel stehen. Verweise auf andere Regeln werden nicht
+/// verfolgt.
+fn rule_literals(rule: &Rule) -> impl Iterator<Item = String> + '_ {
+    rule.expr.iter_top_down().filter_map(|expr| match expr {
+        Expr::Str(literal) | Expr::Insens(literal) if !PUNCTUATION.contains(&literal.as_str()) => {
+            Some(literal)
+        }
+        _ => None,
+    })
+}
+
+/// Wörter wie `gib us` brauchen Wortgrenzen, Zeichen wie `==` nicht
+fn is_word(literal: &str) -> bool {
+    literal.chars().all(|c| c.is_alphabetic() || c == ' ')
+}
+
+/// Längere Literale zuerst, damit z.B. `<=` vor `<` versucht wird
+fn alternatives(mut literals: Vec<String>) -> String {
+    literals.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then(a.cmp(b)));
+    literals
+        .iter()
+        .map(|literal| escape(literal))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn escape(literal: &str) -> String {
+    let mut escaped = String::new();
+    for c in literal.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
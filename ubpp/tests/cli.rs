@@ -0,0 +1,109 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Ungültige Optionen beenden `ubpp` mit einer Fehlermeldung und Exit-Code 1 statt einem Panic
+
+use std::process::Command;
+
+/// Gibt den Exit-Code und die Fehlerausgabe zurück
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ubpp"))
+        .args(args)
+        .current_dir(env!("CARGO_TARGET_TMPDIR"))
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn invalid_dialect() {
+    assert_eq!(
+        run(&["--dialekt", "klingonisch"]),
+        (
+            Some(1),
+            "error: dialect: Unknown dialect `klingonisch`\n".to_string()
+        )
+    );
+    assert_eq!(
+        run(&["--dialekt"]),
+        (
+            Some(1),
+            "error: dialect: --dialekt needs a value\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn missing_allowed_path() {
+    for option in ["--allow-read", "--allow-write"] {
+        let (code, stderr) = run(&[&format!("{}=gits-nöd", option)]);
+        assert_eq!(code, Some(1));
+        assert!(
+            stderr.starts_with("error: permission: Cannot allow `gits-nöd`"),
+            "{}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn invalid_now() {
+    let (code, stderr) = run(&["--jetzt=morn"]);
+    assert_eq!(code, Some(1));
+    assert!(
+        stderr.starts_with("error: type: `morn` is not a date"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn invalid_seed() {
+    let (code, stderr) = run(&["--seed=-1"]);
+    assert_eq!(code, Some(1));
+    assert!(
+        stderr.starts_with("error: argument: `-1` is not a valid seed"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn limits() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("limits.ubpp");
+    std::fs::write(
+        &path,
+        "loss f = funktion (n) { falls (n gliich isch wie 0) { 0 } suscht { f(n minus 1) } };\n\
+         gib us falls (wohr) { falls (wohr) { f(100) } suscht { 1 } } suscht { 2 };",
+    )
+    .unwrap();
+    let path = path.to_str().unwrap();
+    assert_eq!(run(&[path]), (Some(0), String::new()));
+
+    let (code, stderr) = run(&["--max-tiefi=50", path]);
+    assert_eq!(code, Some(1));
+    assert!(
+        stderr.starts_with("error: limit: Depth limit of 50"),
+        "{}",
+        stderr
+    );
+
+    let (code, stderr) = run(&["--max-verschachtelig=3", path]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("nested deeper than 3 levels"), "{}", stderr);
+
+    assert_eq!(
+        run(&["--max-tiefi=viel", path]),
+        (
+            Some(1),
+            "error: argument: `viel` is not a valid limit (invalid digit found in string)\n"
+                .to_string()
+        )
+    );
+}
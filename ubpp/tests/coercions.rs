@@ -0,0 +1,154 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Implizite Umwandlungen. Ohne strikten Modus gilt die Tabelle im README, im strikten Modus ist
+//! jede implizite Umwandlung ein `type` Fehler.
+
+mod common;
+
+use common::eval_after;
+use ubpplib::{
+    step1::{ErrorKind, RuntimeError},
+    step2::has_strict_pragma,
+    step3::Scope,
+};
+
+/// Es gibt kein Literal für `null`, darum steht `nüt` dafür
+const NULL: &str = "loss leer = funktion () { }; loss nüt = leer();";
+
+fn eval_with(source: &str, strict: bool) -> Result<String, RuntimeError> {
+    let mut scope = Scope::default();
+    scope.strict = strict;
+    eval_after(NULL, source, &mut scope)
+}
+
+fn lenient(source: &str) -> String {
+    eval_with(source, false).unwrap()
+}
+
+fn strict(source: &str) -> Result<String, ErrorKind> {
+    eval_with(source, true).map_err(|e| e.kind)
+}
+
+#[test]
+fn lenient_bools() {
+    // Zahlen sind nur wahr, wenn sie 0 sind
+    assert_eq!(lenient("nöd nöd 0"), "true");
+    assert_eq!(lenient("nöd nöd 1"), "false");
+    assert_eq!(lenient("nöd nöd 0.0"), "true");
+    assert_eq!(lenient("nöd nöd 2.5"), "false");
+    assert_eq!(lenient("nöd nöd \"true\""), "true");
+    assert_eq!(lenient("nöd nöd \"false\""), "false");
+    assert_eq!(lenient("nöd nöd nüt"), "false");
+    assert_eq!(lenient("falls (0) { \"ja\" } suscht { \"nei\" }"), "ja");
+    assert_eq!(
+        eval_with("nöd \"ja\"", false).unwrap_err().kind,
+        ErrorKind::Type
+    );
+}
+
+#[test]
+fn lenient_numbers() {
+    assert_eq!(lenient("\"41\" plus 1"), "42");
+    assert_eq!(lenient("\" 2.5 \" mol 2"), "5.0");
+    assert_eq!(lenient("wohr plus 1"), "2");
+    assert_eq!(lenient("falsch plus 1"), "1");
+    assert_eq!(lenient("nüt plus 1"), "1");
+    assert_eq!(lenient("- \"3\""), "-3");
+    assert_eq!(lenient("wurzel(\"16\")"), "4.0");
+    assert_eq!(lenient("zeiche(\"abc\", 1.9)"), "b");
+    assert_eq!(
+        eval_with("\"drü\" minus 1", false).unwrap_err().kind,
+        ErrorKind::Type
+    );
+}
+
+#[test]
+fn lenient_strings() {
+    // Zwei Zeichenketten werden immer zusammengehängt, eine Zeichenkette und ein anderer Wert nur,
+    // wenn die Zeichenkette keine Zahl ist
+    assert_eq!(lenient("\"1\" plus \"2\""), "12");
+    assert_eq!(lenient("\"a\" plus 1"), "a1");
+    assert_eq!(lenient("1 plus \"a\""), "1a");
+    assert_eq!(lenient("\"a\" plus wohr"), "atrue");
+    assert_eq!(lenient("\"a\" plus nüt"), "anull");
+    assert_eq!(lenient("längi(123)"), "3");
+    assert_eq!(lenient("gross(wohr)"), "TRUE");
+}
+
+#[test]
+fn lenient_comparisons() {
+    // Zuerst als Zahlen, sonst als Zeichenketten
+    assert_eq!(lenient("\"10\" isch grösser als \"9\""), "true");
+    assert_eq!(lenient("1 gliich isch wie \"1\""), "true");
+    assert_eq!(lenient("wohr gliich isch wie 1"), "true");
+    assert_eq!(lenient("nüt gliich isch wie 0"), "true");
+    assert_eq!(lenient("\"b\" isch grösser als \"a\""), "true");
+}
+
+#[test]
+fn strict_mode_rejects_implicit_conversions() {
+    assert_eq!(strict("nöd 0"), Err(ErrorKind::Type));
+    assert_eq!(
+        strict("falls (\"true\") { 1 } suscht { 2 }"),
+        Err(ErrorKind::Type)
+    );
+    assert_eq!(strict("wohr und 1"), Err(ErrorKind::Type));
+    assert_eq!(strict("\"41\" plus 1"), Err(ErrorKind::Type));
+    assert_eq!(strict("\"a\" plus 1"), Err(ErrorKind::Type));
+    assert_eq!(strict("nüt plus 1"), Err(ErrorKind::Type));
+    assert_eq!(strict("wohr mol 2"), Err(ErrorKind::Type));
+    assert_eq!(strict("- \"3\""), Err(ErrorKind::Type));
+    assert_eq!(strict("1 gliich isch wie \"1\""), Err(ErrorKind::Type));
+    assert_eq!(strict("wurzel(\"16\")"), Err(ErrorKind::Type));
+    assert_eq!(strict("zeiche(\"abc\", 1.0)"), Err(ErrorKind::Type));
+    assert_eq!(strict("längi(123)"), Err(ErrorKind::Type));
+
+    let error = eval_with("nöd nüt", true).unwrap_err();
+    assert_eq!(error.message, "Cannot use `null` as bool in strict mode");
+}
+
+#[test]
+fn strict_mode_allows_values_of_the_right_type() {
+    assert_eq!(strict("nöd falsch"), Ok("true".to_string()));
+    assert_eq!(strict("1 plus 2.5"), Ok("3.5".to_string()));
+    assert_eq!(strict("\"a\" plus \"b\""), Ok("ab".to_string()));
+    // Zeichenketten werden nicht als Zahlen verglichen
+    assert_eq!(
+        strict("\"10\" isch grösser als \"9\""),
+        Ok("false".to_string())
+    );
+    assert_eq!(strict("1 gliich isch wie 1.0"), Ok("true".to_string()));
+    assert_eq!(strict("wurzel(16)"), Ok("4.0".to_string()));
+    assert_eq!(strict("längi(teile(\"a,b\", \",\"))"), Ok("2".to_string()));
+}
+
+#[test]
+fn strict_mode_converts_with_casts() {
+    assert_eq!(strict("(\"41\" als Zahl) plus 1"), Ok("42".to_string()));
+    assert_eq!(
+        strict("\"a\" plus (1 als Zeicheketti)"),
+        Ok("a1".to_string())
+    );
+    assert_eq!(
+        strict("nöd (\"false\" als Wohretswärt)"),
+        Ok("true".to_string())
+    );
+    assert_eq!(
+        strict("zeiche(\"abc\", 1.9 als Ganzzahl)"),
+        Ok("b".to_string())
+    );
+}
+
+#[test]
+fn strict_pragma() {
+    assert!(has_strict_pragma("/* strikt */\ngib us 1;"));
+    assert!(has_strict_pragma(
+        "/* dialekt: bärndütsch */\n/* strikt */\ngib us 1;"
+    ));
+    assert!(!has_strict_pragma("gib us 1;\n/* strikt */"));
+    assert!(!has_strict_pragma("/* nöd strikt */"));
+}
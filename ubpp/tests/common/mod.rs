@@ -0,0 +1,30 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Hilfsfunktionen, die mehrere Integrationstests brauchen
+
+use ubpplib::{
+    step1::{ErrorKind, RuntimeError},
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+/// Führt `prelude` und danach `source` aus und gibt den letzten Wert als Text zurück. Das
+/// Programm muss gültig sein, ein Parse-Fehler lässt den Test scheitern.
+pub fn eval_after(prelude: &str, source: &str, scope: &mut Scope) -> Result<String, RuntimeError> {
+    let tokens = parse_file(&format!("{}\n{}", prelude, source)).unwrap();
+    eval_tokens(tokens, scope).map(|result| result.to_string())
+}
+
+/// Die Struktur und die Auswahl, die `tests/records.rs` und `tests/enums.rs` voraussetzen
+const DEFINITIONS: &str = "definier e struktur wo Person heisst mit name, alter;
+definier e uswahl wo Form heisst: Kreis(radius), Rechteck(b, h), Leer;";
+
+/// Wie [`eval_after`] mit [`DEFINITIONS`] in einem frischen Scope, ein Fehler ergibt nur seine Art
+#[allow(dead_code)]
+pub fn eval_with_definitions(source: &str) -> Result<String, ErrorKind> {
+    eval_after(DEFINITIONS, source, &mut Scope::default()).map_err(|e| e.kind)
+}
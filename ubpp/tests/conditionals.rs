@@ -0,0 +1,106 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `falls ... suscht falls ... suscht` als Anweisung und als Ausdruck
+
+use ubpplib::{
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    let tokens = parse_file(source).unwrap();
+    eval_tokens(tokens, &mut Scope::default())
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+/// Ordnet `n` mit einer Kette von Anweisungen ein
+fn classify_statement(n: i32) -> String {
+    eval(&format!(
+        "loss n = {};
+        loss art = \"\";
+        falls de n < 0 mach {{ art = \"negativ\"; }}
+        suscht falls de n gliich isch wie 0 mach {{ art = \"null\"; }}
+        suscht falls (n < 10) {{ art = \"chli\"; }}
+        suscht {{ art = \"gross\"; }}
+        art",
+        n
+    ))
+    .unwrap()
+}
+
+/// Wie [`classify_statement`] mit einem Ausdruck
+fn classify_expression(n: i32) -> String {
+    eval(&format!(
+        "loss n = {};
+        falls de n < 0 mach {{ \"negativ\" }}
+        suscht falls de n gliich isch wie 0 mach {{ \"null\" }}
+        suscht falls (n < 10) {{ \"chli\" }}
+        suscht {{ \"gross\" }}",
+        n
+    ))
+    .unwrap()
+}
+
+#[test]
+fn the_first_true_branch_runs() {
+    for (n, expected) in [(-5, "negativ"), (0, "null"), (3, "chli"), (42, "gross")] {
+        assert_eq!(classify_statement(n), expected, "{}", n);
+        assert_eq!(classify_expression(n), expected, "{}", n);
+    }
+    // Auch wenn spätere Bedingungen ebenfalls stimmen
+    assert_eq!(
+        eval("falls (wohr) { \"eis\" } suscht falls (wohr) { \"zwei\" } suscht { \"drü\" }"),
+        Ok("eis".to_string())
+    );
+}
+
+#[test]
+fn later_conditions_are_not_evaluated() {
+    assert_eq!(
+        eval("falls (1 < 2) { \"eis\" } suscht falls (1 / 0 > 1) { \"nie\" } suscht { \"nie\" }"),
+        Ok("eis".to_string())
+    );
+    assert_eq!(
+        eval("falls (1 > 2) { \"nie\" } suscht falls (1 / 0 > 1) { \"nie\" } suscht { \"nie\" }"),
+        Err(ErrorKind::Arithmetic)
+    );
+}
+
+#[test]
+fn without_else_nothing_happens() {
+    assert_eq!(
+        eval(
+            "loss x = 1;
+            falls (falsch) { x = 2; } suscht falls (falsch) { x = 3; }
+            x"
+        ),
+        Ok("1".to_string())
+    );
+}
+
+#[test]
+fn branches_pass_on_errors_and_stop() {
+    assert_eq!(
+        eval(
+            "loss a = falls (falsch) { 1 } suscht falls (wohr) { wirf \"zwei\"; 2 } suscht { 3 };"
+        ),
+        Err(ErrorKind::Thrown)
+    );
+    assert_eq!(
+        eval(
+            "loss i = 0;
+            solang (i < 10) {
+                i = i plus 1;
+                falls (falsch) { } suscht falls (i gliich isch wie 4) { stop; }
+            }
+            i"
+        ),
+        Ok("4".to_string())
+    );
+}
@@ -0,0 +1,280 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Golden-File Tests: Jedes Programm `tests/conformance/<name>.ubpp` wird mit dem `ubpp` Binary
+//! ausgeführt und mit den Dateien daneben verglichen:
+//! - `<name>.stdout`: erwartete Ausgabe (muss es geben, auch wenn sie leer ist)
+//! - `<name>.stdin`: Eingabe für `e frog` (optional)
+//! - `<name>.error`: erwartete Fehlerart, z.B. `no match` (nur wenn das Programm abbricht)
+//!
+//! Nach einer gewollten Änderung werden die Erwartungen mit
+//! `UBPP_BLESS=1 cargo test --test conformance` neu geschrieben.
+
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use pest::Parser;
+use ubpplib::{dialect::Dialect, step2::parse_file, Rule, UBPP};
+
+/// Regeln, die nur im negativen Lookahead von `variable_name` vorkommen und darum nie als
+/// Knoten im Parse-Baum erscheinen
+const LOOKAHEAD_ONLY: &[&str] = &["keyword", "control", "do_name"];
+
+/// Knoten im AST, die der Interpreter in mindestens einem Programm auswerten muss, so wie sie in
+/// der `Debug` Ausgabe der Tokens erscheinen. Eine neue Variante in `step1` gehört hier dazu.
+const EVALUATED_NODES: &[&str] = &[
+    // Token
+    "Break",
+    // Statement
+    "Statement(VariableAssignment(",
+    "Statement(Conditional(",
+    "Statement(Expression(",
+    "Statement(Print(",
+    "Statement(Loop(",
+    "Statement(Match(",
+    "Statement(Try(",
+    "Statement(Throw(",
+    "Statement(Import(",
+    "Statement(WriteFile(",
+    "Statement(Test(",
+    "Statement(Expect(",
+    "Statement(Struct(",
+    "Statement(Enum(",
+    "Statement(FieldAssignment(",
+    // Expression
+    "Atomic(",
+    "Ident(",
+    "LogicOp(",
+    "UnaryOp(",
+    "Comparison(",
+    "BinaryOp(",
+    "Conditional(ConditionalExpression",
+    "Match(MatchExpression",
+    "Input(",
+    "Cast(",
+    "FieldAccess(",
+    "Call(",
+    "Function(",
+    "ReadFile(",
+    "Random(",
+    // LogicOp
+    "And(",
+    "Or(",
+    // UnaryOp
+    "Not(",
+    "Negate(",
+    // Comparison
+    "Smaller(",
+    "SmallerEquals(",
+    "Equals(",
+    "NotEquals(",
+    "Greater(",
+    "GreaterEquals(",
+    // BinaryOp
+    "Plus {",
+    "Minus {",
+    "Mul {",
+    "Div {",
+    "Mod {",
+    "Pow {",
+    // Cast
+    "Cast(String(",
+    "Cast(Number(",
+    "Cast(Integer(",
+    "Cast(Float(",
+    "Cast(Bool(",
+    "Cast(Json(",
+    "Cast(FromJson(",
+];
+
+fn conformance_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+/// Der Quelltext im Zürcher Dialekt
+fn translated_source(program: &Path) -> String {
+    let source = std::fs::read_to_string(program).unwrap();
+    let dialect = Dialect::from_header(&source)
+        .map(Result::unwrap)
+        .unwrap_or_default();
+    dialect.translate(&source)
+}
+
+fn programs() -> Vec<PathBuf> {
+    let mut programs = std::fs::read_dir(conformance_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "ubpp")
+        })
+        .collect::<Vec<_>>();
+    programs.sort();
+    programs
+}
+
+struct Outcome {
+    stdout: String,
+    error: Option<String>,
+}
+
+/// Kopiert das Programm in ein sonst leeres Arbeitsverzeichnis, in dem es lesen und schreiben
+/// darf, und führt es dort aus. Relative Pfade gelten ab der Datei, darum liegen die Module für
+/// `bruuch` als Kopie in `lib/`.
+fn run(program: &Path) -> Outcome {
+    let name = program.file_name().unwrap();
+    let workdir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("conformance")
+        .join(program.file_stem().unwrap());
+    let _ = std::fs::remove_dir_all(&workdir);
+    std::fs::create_dir_all(workdir.join("lib")).unwrap();
+    std::fs::copy(program, workdir.join(name)).unwrap();
+    for module in std::fs::read_dir(conformance_dir().join("lib")).unwrap() {
+        let module = module.unwrap();
+        std::fs::copy(module.path(), workdir.join("lib").join(module.file_name())).unwrap();
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ubpp"))
+        .args([
+            "--seed=0",
+            "--jetzt=18.10.2026 14:30",
+            "--allow-read=.",
+            "--allow-write=.",
+        ])
+        .arg(name)
+        .current_dir(&workdir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdin = std::fs::read(program.with_extension("stdin")).unwrap_or_default();
+    child.stdin.take().unwrap().write_all(&stdin).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("error: "))
+        .map(|error| error.split_once(": ").map_or(error, |(kind, _)| kind))
+        .map(str::to_string);
+    assert_eq!(
+        output.status.success(),
+        error.is_none(),
+        "{}: unexpected exit status, stderr:\n{}",
+        program.display(),
+        stderr
+    );
+    Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        error,
+    }
+}
+
+#[test]
+fn golden_files() {
+    let bless = std::env::var_os("UBPP_BLESS").is_some();
+    let mut failures = vec![];
+    for program in programs() {
+        let outcome = run(&program);
+        let stdout_file = program.with_extension("stdout");
+        let error_file = program.with_extension("error");
+        if bless {
+            std::fs::write(&stdout_file, &outcome.stdout).unwrap();
+            match &outcome.error {
+                Some(kind) => std::fs::write(&error_file, format!("{}\n", kind)).unwrap(),
+                None => {
+                    let _ = std::fs::remove_file(&error_file);
+                }
+            }
+            continue;
+        }
+
+        let Ok(expected_stdout) = std::fs::read_to_string(&stdout_file) else {
+            failures.push(format!(
+                "{}: missing {}",
+                program.display(),
+                stdout_file.display()
+            ));
+            continue;
+        };
+        if outcome.stdout != expected_stdout {
+            failures.push(format!(
+                "{}: stdout differs\n--- expected\n{}--- actual\n{}",
+                program.display(),
+                expected_stdout,
+                outcome.stdout
+            ));
+        }
+        let expected_error = std::fs::read_to_string(&error_file)
+            .ok()
+            .map(|kind| kind.trim().to_string());
+        if outcome.error != expected_error {
+            failures.push(format!(
+                "{}: expected error {:?}, got {:?}",
+                program.display(),
+                expected_error,
+                outcome.error
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn every_grammar_rule_is_covered() {
+    let grammar =
+        std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../ubpp.pest"))
+            .unwrap();
+    let mut uncovered = grammar
+        .lines()
+        .filter_map(|line| {
+            let (name, definition) = line.split_once('=')?;
+            let name = name.trim();
+            let is_rule = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+            // Stille Regeln (`_{`) erzeugen keine Knoten
+            let is_silent = definition.trim_start().starts_with('_');
+            (is_rule && !is_silent && !LOOKAHEAD_ONLY.contains(&name)).then(|| name.to_string())
+        })
+        .collect::<BTreeSet<_>>();
+
+    for program in programs() {
+        let source = translated_source(&program);
+        // Programme mit Syntaxfehlern prüft `golden_files`
+        let Ok(pairs) = UBPP::parse(Rule::file, &source) else {
+            continue;
+        };
+        for pair in pairs.flatten() {
+            uncovered.remove(&format!("{:?}", pair.as_rule()));
+        }
+    }
+    assert!(
+        uncovered.is_empty(),
+        "grammar rules without a conformance program: {:?}",
+        uncovered
+    );
+}
+
+#[test]
+fn every_evaluator_node_is_covered() {
+    let mut uncovered = EVALUATED_NODES.iter().copied().collect::<BTreeSet<_>>();
+    for program in programs() {
+        let Ok(tokens) = parse_file(&translated_source(&program)) else {
+            continue;
+        };
+        let tree = format!("{:?}", tokens);
+        uncovered.retain(|node| !tree.contains(node));
+    }
+    assert!(
+        uncovered.is_empty(),
+        "AST nodes without a conformance program: {:?}",
+        uncovered
+    );
+}
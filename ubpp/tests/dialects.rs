@@ -0,0 +1,164 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Jeder Dialekt muss denselben AST ergeben wie das gleiche Programm auf Züritüütsch
+
+use pest::error::LineColLocation;
+use ubpplib::{
+    dialect::Dialect,
+    step2::{parse_file, MAX_NESTING},
+};
+
+const ZURICH: &str = r#"
+loss x = 2;
+falls (x isch kliiner als 3 und nöd (x gliich isch wie 1) odr x isch grösser oder gliich wie 10) {
+    gib us "a";
+} suscht {
+    gib us x mol 3 als Zeicheketti;
+}
+solang de x isch kliiner als 5 mach { x = x plus 1; stop; }
+gib us wohr als Zahl;
+gib us lueg ob x { isch 2 => "b", susch => "c" };
+probier { wirf "a"; } fang f { gib us f; }
+"#;
+
+const BERNESE: &str = r#"
+la x = 2;
+we (x isch chliner aus 3 und nid (x glych isch wie 1) oder x isch grösser oder glych wie 10) {
+    gib uus "a";
+} süsch {
+    gib uus x mau 3 aus Zeichechetti;
+}
+solang de x isch chliner aus 5 mach { x = x plus 1; stop; }
+gib uus wahr aus Zau;
+gib uus lue ob x { isch 2 => "b", angers => "c" };
+probier { schmeiss "a"; } fah f { gib uus f; }
+"#;
+
+const BASEL: &str = r#"
+loss x = 2;
+wenn (x isch glainer als 3 und nit (x glych isch wie 1) oder x isch gresser oder glych wie 10) {
+    gib uus "a";
+} sunscht {
+    gib uus x mool 3 als Zaichekette;
+}
+solang de x isch glainer als 5 mach { x = x plus 1; stop; }
+gib uus woor als Zahl;
+gib uus lueg ob x { isch 2 => "b", anderscht => "c" };
+probier { schmeiss "a"; } fang f { gib uus f; }
+"#;
+
+const HIGH_GERMAN: &str = r#"
+lass x = 2;
+wenn (x ist kleiner als 3 und nicht (x gleich ist wie 1) oder x ist größer oder gleich wie 10) {
+    gib aus "a";
+} sonst {
+    gib aus x mal 3 als Zeichenkette;
+}
+solange das x ist kleiner als 5 mache { x = x plus 1; halt; }
+gib aus wahr als Zahl;
+gib aus prüfe ob x { ist 2 => "b", ansonsten => "c" };
+versuche { wirf "a"; } fange f { gib aus f; }
+"#;
+
+const ENGLISH: &str = r#"
+let x = 2;
+if (x is less than 3 and not (x is equal to 1) or x is greater than or equal to 10) {
+    print "a";
+} else {
+    print x times 3 as String;
+}
+while the x is less than 5 do { x = x plus 1; break; }
+print true as Number;
+print match x { is 2 => "b", otherwise => "c" };
+try { throw "a"; } catch f { print f; }
+"#;
+
+fn ast(dialect: Dialect, source: &str) -> String {
+    format!("{:?}", parse_file(&dialect.translate(source)).unwrap())
+}
+
+#[test]
+fn every_dialect_gives_the_zurich_ast() {
+    let zurich = ast(Dialect::Zurich, ZURICH);
+    for (dialect, source) in [
+        (Dialect::Bernese, BERNESE),
+        (Dialect::Basel, BASEL),
+        (Dialect::HighGerman, HIGH_GERMAN),
+        (Dialect::English, ENGLISH),
+    ] {
+        assert_eq!(ast(dialect, source), zurich, "{:?}", dialect);
+    }
+}
+
+/// Die Grammatik kennt nur Züritüütsch, darum sind dessen Schlüsselwörter in jedem Dialekt
+/// reserviert
+#[test]
+fn zurich_keywords_are_reserved_in_every_dialect() {
+    for (dialect, source) in [
+        (Dialect::English, "let falls = 1;"),
+        (Dialect::HighGerman, "lass solang = 1;"),
+        (Dialect::Bernese, "la loss = 1;"),
+    ] {
+        assert!(
+            parse_file(&dialect.translate(source)).is_err(),
+            "{:?}",
+            dialect
+        );
+    }
+    assert_eq!(
+        ast(Dialect::English, "if (true) { print 1; }"),
+        ast(Dialect::English, "falls (true) { print 1; }")
+    );
+}
+
+#[test]
+fn strings_are_not_translated() {
+    assert_eq!(
+        ast(Dialect::English, r#"print "say \"if\" or \\"; print "if";"#),
+        ast(
+            Dialect::Zurich,
+            r#"gib us "say \"if\" or \\"; gib us "if";"#
+        )
+    );
+}
+
+#[test]
+fn parse_errors_point_into_the_original() {
+    let source = "let x = 1;\nprint x is greater than or equal to 10 10;";
+    let error = Dialect::English.parse(source, MAX_NESTING).unwrap_err();
+    // Die Zahl `10 10` steht in der Übersetzung weiter rechts
+    let line = source.lines().nth(1).unwrap();
+    let start = line.find("10").unwrap() + 1;
+    assert_eq!(
+        error.line_col,
+        LineColLocation::Span((2, start), (2, start + "10 10".len()))
+    );
+    let message = error.to_string();
+    assert!(
+        message.contains("print x is greater than or equal to 10 10;"),
+        "{}",
+        message
+    );
+    assert!(!message.contains("isch"), "{}", message);
+
+    let source = "if (true) { print 1 }";
+    let error = Dialect::English.parse(source, MAX_NESTING).unwrap_err();
+    let column = source.find('}').unwrap() + 1;
+    assert_eq!(error.line_col, LineColLocation::Pos((1, column)));
+}
+
+#[test]
+fn phrases_do_not_span_lines() {
+    assert_eq!(
+        Dialect::English.translate("x is  less\tthan 3"),
+        "x isch kliiner als 3"
+    );
+    assert_eq!(
+        Dialect::English.translate("x is\nless than 3"),
+        "x isch\nless than 3"
+    );
+}
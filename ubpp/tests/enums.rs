@@ -0,0 +1,85 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod common;
+
+use common::eval_with_definitions as eval;
+use ubpplib::{step1::ErrorKind, step2::parse_file};
+
+#[test]
+fn construction() {
+    assert_eq!(eval("Rechteck(3, 4)"), Ok("Rechteck(3, 4)".to_string()));
+    assert_eq!(eval("Leer"), Ok("Leer".to_string()));
+    assert_eq!(eval("loss k = Kreis(2); k.radius"), Ok("2".to_string()));
+    assert_eq!(eval("Kreis()"), Err(ErrorKind::Argument));
+    assert_eq!(eval("Leer()"), Err(ErrorKind::Type));
+}
+
+#[test]
+fn match_binds_payload_fields() {
+    let area = "loss fläche = funktion (form) {
+        lueg ob form {
+            isch Kreis(r) => 3 mol r mol r,
+            isch Rechteck(b, h) => b mol h,
+            isch Leer => 0,
+        }
+    };";
+    assert_eq!(
+        eval(&format!("{} fläche(Rechteck(3, 4))", area)),
+        Ok("12".to_string())
+    );
+    assert_eq!(
+        eval(&format!("{} fläche(Kreis(2))", area)),
+        Ok("12".to_string())
+    );
+    // Die Bindung gilt nur im Arm
+    assert_eq!(
+        eval("loss r = 1; lueg ob Kreis(5) { isch Kreis(r) => r = r plus 1; } r"),
+        Ok("1".to_string())
+    );
+    assert_eq!(
+        eval("lueg ob Kreis(5) { isch Rechteck => \"eckig\", isch Kreis => \"rund\" }"),
+        Ok("rund".to_string())
+    );
+    assert_eq!(
+        eval("lueg ob Kreis(5) { isch Kreis(a, b) => a }"),
+        Err(ErrorKind::Argument)
+    );
+}
+
+#[test]
+fn variants_are_only_equal_or_not() {
+    assert_eq!(eval("Kreis(2) == Kreis(2.0)"), Ok("true".to_string()));
+    assert_eq!(eval("Kreis(2) != Leer"), Ok("true".to_string()));
+    for source in ["Kreis(1) < Kreis(2)", "Leer >= Leer", "Kreis(1) > 0"] {
+        assert_eq!(eval(source), Err(ErrorKind::Type), "{}", source);
+    }
+}
+
+#[test]
+fn unmatched_variant_is_an_error() {
+    assert_eq!(
+        eval("lueg ob Kreis(1) { isch Leer => gib us \"leer\"; }"),
+        Err(ErrorKind::NoMatch)
+    );
+    assert_eq!(
+        eval("lueg ob Kreis(1) { isch Leer => gib us \"leer\";, susch => gib us \"anders\"; }"),
+        Ok("null".to_string())
+    );
+    // Bei anderen Werten passiert weiterhin nichts
+    assert_eq!(
+        eval("lueg ob 1 { isch 2 => gib us \"zwei\"; }"),
+        Ok("null".to_string())
+    );
+}
+
+#[test]
+fn variants_are_defined_once() {
+    assert_eq!(eval("loss Leer = 1;"), Err(ErrorKind::AlreadyDefined));
+    assert!(parse_file("definier e uswahl wo A heisst: B, B;").is_err());
+    assert!(parse_file("definier e uswahl wo A heisst: B(x, x);").is_err());
+    assert!(parse_file("lueg ob x { isch B(y, y) => y }").is_err());
+}
@@ -0,0 +1,106 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `wirf` und `probier ... fang`
+
+use ubpplib::{
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    let tokens = parse_file(source).unwrap();
+    let mut scope = Scope::default();
+    scope.step_limit = Some(1_000);
+    eval_tokens(tokens, &mut scope)
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+#[test]
+fn uncaught_throw_is_an_error() {
+    assert_eq!(eval("wirf \"Abbruch\"; 1"), Err(ErrorKind::Thrown));
+    assert_eq!(eval("loss x = 1; wirf x plus 1;"), Err(ErrorKind::Thrown));
+}
+
+#[test]
+fn catch_sees_kind_and_message() {
+    assert_eq!(
+        eval("loss a = \"\"; probier { wirf \"oje\"; } fang f { a = f.art plus \"/\" plus f.meldig; } a"),
+        Ok("thrown/oje".to_string())
+    );
+    assert_eq!(
+        eval("loss a = \"\"; probier { wirf 42; } fang f { a = f.meldig; } a"),
+        Ok("42".to_string())
+    );
+    // Der Rest vom `probier` Block läuft nach dem Fehler nicht mehr
+    assert_eq!(
+        eval("loss a = 0; probier { wirf \"x\"; a = 1; } fang f { a = a plus 2; } a"),
+        Ok("2".to_string())
+    );
+    // Ohne Fehler läuft `fang` nie
+    assert_eq!(
+        eval("loss a = 0; probier { a = 1; } fang f { a = 2; } a"),
+        Ok("1".to_string())
+    );
+}
+
+#[test]
+fn runtime_errors_are_caught() {
+    let kind = |body: &str| {
+        eval(&format!(
+            "loss a = \"\"; probier {{ {} }} fang f {{ a = f.art; }} a",
+            body
+        ))
+    };
+    assert_eq!(kind("\"abc\" als Zahl;"), Ok("type".to_string()));
+    assert_eq!(kind("1 durch 0;"), Ok("arithmetic".to_string()));
+    assert_eq!(
+        kind("falls (falsch) { loss x = 1; } gib us x;"),
+        Ok("undefined".to_string())
+    );
+}
+
+#[test]
+fn error_variable_is_scoped_to_catch() {
+    assert_eq!(
+        eval("loss f = \"vorher\"; probier { wirf 1; } fang f { f = 2; } f"),
+        Ok("vorher".to_string())
+    );
+    assert_eq!(
+        eval("probier { wirf 1; } fang f { } gib us f;"),
+        Err(ErrorKind::Undefined)
+    );
+}
+
+#[test]
+fn nested_catch_can_throw_again() {
+    assert_eq!(
+        eval(
+            "loss a = \"\";
+            probier {
+                probier { wirf \"inne\"; } fang f { wirf \"usse: \" plus f.meldig; }
+            } fang f {
+                a = f.meldig;
+            }
+            a"
+        ),
+        Ok("usse: inne".to_string())
+    );
+    assert_eq!(
+        eval("probier { wirf \"a\"; } fang f { wirf f.meldig; }"),
+        Err(ErrorKind::Thrown)
+    );
+}
+
+#[test]
+fn step_limit_is_not_caught() {
+    assert_eq!(
+        eval("probier { solang de wohr mach {} } fang f { }"),
+        Err(ErrorKind::Limit)
+    );
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval(source: &str) -> String {
+    let tokens = parse_file(source).unwrap();
+    eval_tokens(tokens, &mut Scope::default())
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn arithmetic_operators() {
+    assert_eq!(eval("1 plus 2"), "3");
+    assert_eq!(eval("1 + 2"), "3");
+    assert_eq!(eval("5 minus 7"), "-2");
+    assert_eq!(eval("5 - 7"), "-2");
+    assert_eq!(eval("3 mol 4"), "12");
+    assert_eq!(eval("3 * 4"), "12");
+    assert_eq!(eval("12 durch 4"), "3");
+    assert_eq!(eval("12 / 8"), "1.5");
+    assert_eq!(eval("7 rest 3"), "1");
+    assert_eq!(eval("7 % 3"), "1");
+    assert_eq!(eval("2 hoch 10"), "1024");
+    assert_eq!(eval("2 ** 10"), "1024");
+}
+
+#[test]
+fn comparison_operators() {
+    assert_eq!(eval("1 gliich isch wie 1"), "true");
+    assert_eq!(eval("1 isch gliich wie 2"), "false");
+    assert_eq!(eval("1 == 1"), "true");
+    assert_eq!(eval("1 ungliich isch wie 2"), "true");
+    assert_eq!(eval("1 isch ungliich wie 1"), "false");
+    assert_eq!(eval("1 != 2"), "true");
+    assert_eq!(eval("1 isch kliiner als 2"), "true");
+    assert_eq!(eval("2 kliiner isch als 2"), "false");
+    assert_eq!(eval("1 < 2"), "true");
+    assert_eq!(eval("2 isch kliiner oder gliich wie 2"), "true");
+    assert_eq!(eval("3 kliiner oder gliich isch wie 2"), "false");
+    assert_eq!(eval("2 <= 2"), "true");
+    assert_eq!(eval("3 isch grösser als 2"), "true");
+    assert_eq!(eval("2 grösser isch als 2"), "false");
+    assert_eq!(eval("3 > 2"), "true");
+    assert_eq!(eval("2 isch grösser oder gliich wie 2"), "true");
+    assert_eq!(eval("1 grösser oder gliich isch wie 2"), "false");
+    assert_eq!(eval("2 >= 3"), "false");
+}
+
+#[test]
+fn logic_operators() {
+    assert_eq!(eval("wohr und falsch"), "false");
+    assert_eq!(eval("wohr odr falsch"), "true");
+    assert_eq!(eval("wohr und wohr und falsch"), "false");
+    assert_eq!(eval("falsch odr falsch odr wohr"), "true");
+}
+
+#[test]
+fn arithmetic_precedence() {
+    assert_eq!(eval("1 plus 2 mol 3"), "7");
+    assert_eq!(eval("(1 plus 2) mol 3"), "9");
+    assert_eq!(eval("10 minus 4 minus 3"), "3");
+    assert_eq!(eval("2 hoch 3 hoch 2"), "512");
+    assert_eq!(eval("2 mol 3 hoch 2"), "18");
+    assert_eq!(eval("7 minus 6 rest 4"), "5");
+}
+
+#[test]
+fn comparison_binds_weaker_than_arithmetic() {
+    assert_eq!(eval("1 plus 2 gliich isch wie 3"), "true");
+    assert_eq!(eval("2 mol 3 isch grösser als 1 plus 4"), "true");
+    assert_eq!(eval("loss x = 9; x rest 3 gliich isch wie 0"), "true");
+}
+
+#[test]
+fn logic_binds_weaker_than_comparison() {
+    assert_eq!(eval("1 < 2 und 3 > 4"), "false");
+    assert_eq!(eval("1 < 2 odr 3 > 4"), "true");
+    assert_eq!(eval("wohr odr falsch und falsch"), "true");
+    assert_eq!(eval("(wohr odr falsch) und falsch"), "false");
+}
+
+#[test]
+fn cast_applies_to_whole_expression() {
+    assert_eq!(eval("1 plus 2 als Zeicheketti"), "3");
+    assert_eq!(eval("(\"4\" als Zahl) plus 1"), "5");
+}
+
+#[test]
+fn strings_are_concatenated() {
+    assert_eq!(eval("\"a\" plus \"b\""), "ab");
+    assert_eq!(eval("\"x = \" plus 1"), "x = 1");
+}
+
+#[test]
+fn string_escapes() {
+    assert_eq!(eval(r#""sie seit \"hoi\"""#), r#"sie seit "hoi""#);
+    assert_eq!(eval(r#""C:\\temp""#), r"C:\temp");
+    assert_eq!(eval(r#"längi("\"\\")"#), "2");
+    // Andere Zeichen nach einem Backslash sind kein gültiges Programm
+    assert!(parse_file(r#""a\nb""#).is_err());
+    assert!(parse_file(r#""a\""#).is_err());
+}
+
+#[test]
+fn logical_not() {
+    assert_eq!(eval("nöd wohr"), "false");
+    assert_eq!(eval("!falsch"), "true");
+    assert_eq!(eval("nöd nöd wohr"), "true");
+    assert_eq!(eval("nöd 1 gliich isch wie 2"), "true");
+    assert_eq!(eval("nöd wohr und falsch"), "false");
+    assert_eq!(eval("nöd (wohr und falsch)"), "true");
+    assert_eq!(eval("falsch odr nöd falsch"), "true");
+}
+
+#[test]
+fn unary_minus() {
+    assert_eq!(eval("loss x = 3; -x"), "-3");
+    assert_eq!(eval("loss a = 1; loss b = 2; -(a plus b)"), "-3");
+    assert_eq!(eval("- -2"), "2");
+    assert_eq!(eval("-2 hoch 2"), "-4");
+    assert_eq!(eval("(-2) hoch 2"), "4");
+    assert_eq!(eval("-2 mol 3"), "-6");
+    assert_eq!(eval("2 hoch -1"), "0.5");
+    assert_eq!(eval("1 minus -1"), "2");
+    assert_eq!(eval("-1.5"), "-1.5");
+    assert_eq!(eval("-1 isch kliiner als 0"), "true");
+}
+
+#[test]
+fn math_builtins() {
+    assert_eq!(eval("wurzel(16)"), "4.0");
+    assert_eq!(eval("wurzel(\"2.25\")"), "1.5");
+    assert_eq!(eval("abs(-3)"), "3");
+    assert_eq!(eval("rund(2.5) plus abrunde(2.7) plus ufrunde(2.1)"), "8");
+    assert_eq!(eval("max(1, 7.5, 3)"), "7.5");
+    assert_eq!(eval("min(4, 2)"), "2");
+    assert_eq!(eval("rund(sin(pi / 2))"), "1");
+    assert_eq!(eval("log(e)"), "1.0");
+    assert_eq!(eval("log(100, 10)"), "2.0");
+    assert_eq!(eval("loss max = 3; max"), "3");
+}
+
+#[test]
+fn string_builtins() {
+    assert_eq!(eval("längi(\"Grüezi\")"), "6");
+    assert_eq!(eval("längi(\"👨‍👩‍👧\")"), "1");
+    assert_eq!(eval("zeiche(\"Bärn\", 1)"), "ä");
+    assert_eq!(eval("usschnitt(\"Chuchichäschtli\", 5, 5)"), "ichäs");
+    assert_eq!(eval("gross(\"grüezi\") plus chlii(\"ZÜRI\")"), "GRÜEZIzüri");
+    assert_eq!(eval("verbinde(teile(\" a,b \", \",\"), \"-\")"), " a-b ");
+    assert_eq!(eval("trimm(\" a \")"), "a");
+    assert_eq!(
+        eval("enthaltet(\"Hallo\", \"ll\") und startetMit(\"Hallo\", \"Ha\")"),
+        "true"
+    );
+    assert_eq!(
+        eval("ersetz(\"Hallo\", \"l\", \"L\") plus wiederhol(\"!\", 2)"),
+        "HaLLo!!"
+    );
+    assert_eq!(
+        eval("loss a = 0; probier { zeiche(\"abc\", 3); } fang f { a = f.art; } a"),
+        "index"
+    );
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    builtins::Permissions,
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval(source: &str, permissions: Permissions) -> Result<String, ErrorKind> {
+    let tokens = parse_file(source).unwrap();
+    let mut scope = Scope::default();
+    scope.permissions = permissions;
+    eval_tokens(tokens, &mut scope)
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+#[test]
+fn access_is_denied_by_default() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    std::fs::write(dir.join("a.txt"), "hallo").unwrap();
+    let file = dir.join("a.txt").display().to_string();
+    assert_eq!(
+        eval(&format!("lies datei \"{}\"", file), Permissions::default()),
+        Err(ErrorKind::Permission)
+    );
+    assert_eq!(
+        eval(
+            &format!("schriib \"x\" in datei \"{}\";", file),
+            Permissions::default()
+        ),
+        Err(ErrorKind::Permission)
+    );
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "hallo");
+}
+
+#[test]
+fn imports_need_permission() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    std::fs::write(dir.join("geheim.ubpp"), "passwort: hunter2").unwrap();
+    let source = format!(
+        "loss meldig = \"\"; probier {{ bruuch \"{}\"; }} fang f {{ meldig = f.meldig; }} meldig",
+        dir.join("geheim.ubpp").display()
+    );
+    let message = eval(&source, Permissions::default()).unwrap();
+    assert!(message.starts_with("No permission to read"), "{}", message);
+
+    // Auch eine Fehlermeldung darf den Inhalt nicht verraten
+    let mut permissions = Permissions::default();
+    permissions.allow_read(dir).unwrap();
+    let message = eval(&source, permissions).unwrap();
+    assert!(message.starts_with("Cannot parse"), "{}", message);
+    assert!(!message.contains("hunter2"), "{}", message);
+}
+
+#[test]
+fn read_and_write_allowed_paths() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    let mut permissions = Permissions::default();
+    permissions.allow_read(dir).unwrap();
+    permissions.allow_write(dir).unwrap();
+    let file = dir.join("b.txt").display().to_string();
+    let source = format!(
+        "schriib \"eins\" in datei \"{0}\"; häng \"\nzwei\" a datei \"{0}\"; \
+         längi(zeilene(\"{0}\")) plus \" \" plus lies datei \"{0}\"",
+        file
+    );
+    assert_eq!(eval(&source, permissions.clone()).unwrap(), "2 eins\nzwei");
+    assert_eq!(
+        eval(&format!("existiert(\"{}\")", file), permissions.clone()).unwrap(),
+        "true"
+    );
+    // Mit `..` kommt man nicht aus dem freigegebenen Verzeichnis heraus
+    let outside = dir.join("../ubpp-outside.txt").display().to_string();
+    assert_eq!(
+        eval(&format!("lies datei \"{}\"", outside), permissions),
+        Err(ErrorKind::Permission)
+    );
+}
+
+#[test]
+fn existence_needs_permission() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    for path in [dir.join("a.txt"), dir.join("gits-nöd/a.txt")] {
+        let source = format!("existiert(\"{}\")", path.display());
+        assert_eq!(
+            eval(&source, Permissions::default()),
+            Err(ErrorKind::Permission)
+        );
+        let mut permissions = Permissions::default();
+        permissions.allow_read(dir).unwrap();
+        assert_eq!(eval(&source, permissions), Ok("false".to_string()));
+    }
+}
+
+#[test]
+fn relative_paths_start_at_the_program() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(dir.join("data/c.txt"), "hallo").unwrap();
+    std::fs::write(dir.join("data/modul.ubpp"), "loss gruess = \"hoi\";").unwrap();
+    let tokens = parse_file(
+        "bruuch \"data/modul.ubpp\"; \
+         schriib lies datei \"data/c.txt\" in datei \"d.txt\"; \
+         modul.gruess plus existiert(\"d.txt\")",
+    )
+    .unwrap();
+    let mut scope = Scope::new(dir.join("programm.ubpp"));
+    scope.permissions.allow_read(dir).unwrap();
+    scope.permissions.allow_write(dir).unwrap();
+    let result = eval_tokens(tokens, &mut scope).unwrap();
+    assert_eq!(result.to_string(), "hoitrue");
+    assert_eq!(std::fs::read_to_string(dir.join("d.txt")).unwrap(), "hallo");
+}
+
+#[test]
+fn imports_do_not_overwrite_variables() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    std::fs::write(
+        dir.join("hilf.ubpp"),
+        "schriib \"glade\" in datei \"glade.txt\"; loss x = 1;",
+    )
+    .unwrap();
+    let eval = |source: &str| {
+        let mut scope = Scope::new(dir.join("programm.ubpp"));
+        scope.permissions.allow_read(dir).unwrap();
+        scope.permissions.allow_write(dir).unwrap();
+        eval_tokens(parse_file(source).unwrap(), &mut scope)
+            .map(|result| result.to_string())
+            .map_err(|e| e.kind)
+    };
+    // Das Modul läuft gar nicht erst
+    assert_eq!(
+        eval("loss hilf = 3; bruuch \"hilf.ubpp\";"),
+        Err(ErrorKind::AlreadyDefined)
+    );
+    assert!(!dir.join("glade.txt").exists());
+    assert_eq!(
+        eval("loss hilf = 3; probier { bruuch \"hilf.ubpp\"; } fang f { } hilf"),
+        Ok("3".to_string())
+    );
+    assert_eq!(
+        eval("bruuch \"hilf.ubpp\"; loss hilf = 3;"),
+        Err(ErrorKind::AlreadyDefined)
+    );
+    assert_eq!(
+        eval("bruuch \"hilf.ubpp\"; bruuch \"hilf.ubpp\";"),
+        Err(ErrorKind::AlreadyDefined)
+    );
+    assert_eq!(eval("bruuch \"hilf.ubpp\"; hilf.x"), Ok("1".to_string()));
+}
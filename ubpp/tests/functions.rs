@@ -0,0 +1,118 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    resolve::resolve,
+    step1::{ErrorKind, Expression, Statement, Token},
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    let tokens = parse_file(source).unwrap();
+    eval_tokens(tokens, &mut Scope::default())
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+#[test]
+fn functions_are_values() {
+    assert_eq!(
+        eval("loss verdopple = funktion (x) { x mol 2 }; verdopple(21)"),
+        Ok("42".to_string())
+    );
+    assert_eq!(
+        eval("loss f = funktion (a, b) { a plus b }; f"),
+        Ok("<funktion (a, b)>".to_string())
+    );
+    assert_eq!(
+        eval("loss f = funktion () { loss x = 1; }; f()"),
+        Ok("null".to_string())
+    );
+}
+
+#[test]
+fn closures_capture_the_defining_scope() {
+    // Eingefangen wird der Wert bei der Definition
+    assert_eq!(
+        eval("loss n = 3; loss f = funktion (x) { x mol n }; n = 10; f(2)"),
+        Ok("6".to_string())
+    );
+    assert_eq!(
+        eval(
+            "loss addierer = funktion (n) { funktion (x) { x plus n } };
+            loss dazu5 = addierer(5);
+            dazu5(3)"
+        ),
+        Ok("8".to_string())
+    );
+    // Zuweisungen in der Funktion ändern die äusseren Variablen nicht
+    assert_eq!(
+        eval("loss x = 1; loss f = funktion () { x = 2; x }; f() plus x"),
+        Ok("3".to_string())
+    );
+}
+
+#[test]
+fn only_captured_variables_are_visible() {
+    // Weder später definierte Variablen noch die des Aufrufers
+    assert_eq!(
+        eval("loss f = funktion () { y }; loss y = 2; f()"),
+        Err(ErrorKind::Undefined)
+    );
+    assert_eq!(
+        eval("loss f = funktion () { y }; loss g = funktion (y) { f() }; g(1)"),
+        Err(ErrorKind::Undefined)
+    );
+    // Eingebaute Funktionen braucht es nicht einzufangen
+    assert_eq!(
+        eval("loss f = funktion (x) { max(x, pi) }; f(4)"),
+        Ok("4".to_string())
+    );
+}
+
+#[test]
+fn functions_capture_only_what_they_read() {
+    let tokens = parse_file(
+        "loss a = 1; loss b = 2;
+        loss f = funktion (x) { loss y = x plus a; funktion (z) { y plus z plus f } };",
+    )
+    .unwrap();
+    let program = resolve(tokens, &mut Scope::default()).unwrap();
+    let Token::Statement(Statement::VariableAssignment(assignment)) = &program.tokens()[2] else {
+        panic!("{:?}", program.tokens()[2]);
+    };
+    let Expression::Function(definition) = &assignment.value else {
+        panic!("{:?}", assignment.value);
+    };
+    // Die Slots folgen der Reihenfolge der Namen: `a`, `b`, `f`, `x`, `y`, `z`. `f` sieht sich
+    // selbst über seinen Slot, `x` ist ein Parameter und `z` gehört der inneren Funktion. `y`
+    // hat bei der Definition noch keinen Wert und fehlt darum in der Closure.
+    assert_eq!(definition.own_slot, Some(2));
+    assert_eq!(definition.captures, [0, 4]);
+}
+
+#[test]
+fn functions_can_be_recursive() {
+    assert_eq!(
+        eval(
+            "loss fak = funktion (n) {
+                falls de n <= 1 mach { 1 } suscht { n mol fak(n minus 1) }
+            };
+            fak(10)"
+        ),
+        Ok("3628800".to_string())
+    );
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+    assert_eq!(
+        eval("loss f = funktion (x) { x }; f(1, 2)"),
+        Err(ErrorKind::Argument)
+    );
+    assert!(parse_file("loss f = funktion (x, x) { x };").is_err());
+}
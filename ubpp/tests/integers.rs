@@ -0,0 +1,59 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Ganzzahlen mit beliebiger Genauigkeit und Kommazahlen
+
+mod common;
+
+use common::eval_after;
+use ubpplib::{step1::ErrorKind, step3::Scope};
+
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    eval_after("", source, &mut Scope::default()).map_err(|e| e.kind)
+}
+
+fn ok(source: &str) -> String {
+    eval(source).unwrap()
+}
+
+#[test]
+fn integers_do_not_overflow() {
+    assert_eq!(ok("9223372036854775807 plus 1"), "9223372036854775808");
+    assert_eq!(ok("2 hoch 100"), "1267650600228229401496703205376");
+    assert_eq!(ok("-9223372036854775808 mol 4"), "-36893488147419103232");
+    assert_eq!(ok("(2 hoch 70) durch (2 hoch 68)"), "4");
+}
+
+#[test]
+fn integers_stay_integers_when_exact() {
+    assert_eq!(ok("12 durch 4"), "3");
+    assert_eq!(ok("7 durch 2"), "3.5");
+    assert_eq!(ok("7 rest 3"), "1");
+    assert_eq!(ok("-7 rest 3"), "-1");
+    assert_eq!(ok("2 hoch -2"), "0.25");
+}
+
+#[test]
+fn floats_mix_with_integers() {
+    assert_eq!(ok("1 plus 0.5"), "1.5");
+    assert_eq!(ok("2 mol 1.5"), "3.0");
+    assert_eq!(ok("7.5 rest 2"), "1.5");
+    assert_eq!(ok("1.0 gliich isch wie 1"), "true");
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    for source in [
+        "10 durch 0",
+        "10 rest 0",
+        "10 durch 0.0",
+        "10 rest 0.0",
+        "10.5 durch 0",
+        "0 durch 0",
+    ] {
+        assert_eq!(eval(source), Err(ErrorKind::Arithmetic), "{}", source);
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ubpplib::{
+    builtins::Permissions,
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Führt `source` aus, wobei `daten` den Inhalt von `json` enthält
+fn eval_with_json(json: &str, source: &str) -> Result<String, ErrorKind> {
+    let dir = std::env::temp_dir().join(format!("ubpp-json-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join(format!(
+        "{}.json",
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&file, json).unwrap();
+
+    let mut permissions = Permissions::default();
+    permissions.allow_read(&dir).unwrap();
+    let mut scope = Scope::default();
+    scope.permissions = permissions;
+    let source = format!(
+        "loss daten = lies datei \"{}\" vo json; {}",
+        file.display(),
+        source
+    );
+    let tokens = parse_file(&source).unwrap();
+    eval_tokens(tokens, &mut scope)
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+#[test]
+fn parse_values() {
+    let json = r#"{"name": "Anna", "alter": 12, "noten": [5.5, 6, null], "dabei": true}"#;
+    assert_eq!(eval_with_json(json, "daten.name").unwrap(), "Anna");
+    assert_eq!(eval_with_json(json, "daten.alter plus 1").unwrap(), "13");
+    assert_eq!(
+        eval_with_json(json, "element(daten.noten, 0)").unwrap(),
+        "5.5"
+    );
+    assert_eq!(
+        eval_with_json(json, "element(daten.noten, 2)").unwrap(),
+        "null"
+    );
+    assert_eq!(eval_with_json(json, "daten.dabei").unwrap(), "true");
+    assert_eq!(
+        eval_with_json("123456789012345678901234567890", "daten plus 1").unwrap(),
+        "123456789012345678901234567891"
+    );
+}
+
+#[test]
+fn serialize_values() {
+    let json = r#"{"b": [1, 2.5, null, false], "a": {"x": "y"}}"#;
+    assert_eq!(
+        eval_with_json(json, "daten als json").unwrap(),
+        r#"{"a":{"x":"y"},"b":[1,2.5,null,false]}"#
+    );
+    assert_eq!(
+        eval_with_json("[]", "teile(\"a,b\", \",\") als json").unwrap(),
+        r#"["a","b"]"#
+    );
+}
+
+#[test]
+fn compare_values() {
+    let json = r#"{"a": [1, 2], "b": [1, 2.0], "c": [2, 1], "d": {"x": 1}, "e": {"x": "1"}}"#;
+    assert_eq!(
+        eval_with_json(json, "daten.a gliich isch wie daten.b"),
+        Ok("true".to_string())
+    );
+    assert_eq!(
+        eval_with_json(json, "daten.a gliich isch wie daten.c"),
+        Ok("false".to_string())
+    );
+    assert_eq!(
+        eval_with_json(json, "daten.d gliich isch wie daten.e"),
+        Ok("true".to_string())
+    );
+    assert_eq!(
+        eval_with_json(json, "daten.a gliich isch wie daten.d"),
+        Ok("false".to_string())
+    );
+    assert_eq!(
+        eval_with_json(json, "wurzel gliich isch wie 1"),
+        Err(ErrorKind::Type)
+    );
+    // Eine Reihenfolge haben Listen und Maps nicht
+    for source in [
+        "daten.a < daten.c",
+        "daten.a <= daten.b",
+        "daten.d isch grösser als daten.e",
+        "daten.a > 1",
+    ] {
+        assert_eq!(
+            eval_with_json(json, source),
+            Err(ErrorKind::Type),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn invalid_json_is_a_runtime_error() {
+    assert_eq!(eval_with_json("{kaputt", "daten"), Err(ErrorKind::Json));
+    assert_eq!(
+        eval_with_json(
+            "null",
+            "loss art = 0; probier { \"[1,\" vo json; } fang f { art = f.art; } art"
+        )
+        .unwrap(),
+        "json"
+    );
+}
@@ -0,0 +1,297 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Startet `ubpp kernel` und spricht mit ihm wie ein Jupyter-Client über localhost
+
+use std::{
+    net::TcpListener,
+    path::Path,
+    process::{Child, Command},
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use ubpplib::kernel::{
+    message::{Message, Session},
+    zmtp::{Connection, SocketType},
+};
+
+const KEY: &str = "geheim";
+
+struct Client {
+    kernel: Child,
+    session: Session,
+    shell: Connection,
+    control: Connection,
+    stdin: Connection,
+    iopub: Connection,
+    hb: Connection,
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.kernel.kill();
+        let _ = self.kernel.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Versucht es, bis der Kernel seine Sockets geöffnet hat
+fn connect(port: u16, socket_type: SocketType) -> Connection {
+    let start = Instant::now();
+    loop {
+        match Connection::connect(("127.0.0.1", port), socket_type, b"client") {
+            Ok(connection) => {
+                connection
+                    .set_read_timeout(Some(Duration::from_secs(10)))
+                    .unwrap();
+                return connection;
+            }
+            Err(e) if start.elapsed() > Duration::from_secs(10) => panic!("{}", e),
+            Err(_) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+impl Client {
+    fn start(name: &str) -> Client {
+        let ports = [(); 5].map(|_| free_port());
+        let connection = json!({
+            "transport": "tcp",
+            "ip": "127.0.0.1",
+            "shell_port": ports[0],
+            "iopub_port": ports[1],
+            "stdin_port": ports[2],
+            "control_port": ports[3],
+            "hb_port": ports[4],
+            "signature_scheme": "hmac-sha256",
+            "key": KEY,
+        });
+        let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("kernel-{}.json", name));
+        std::fs::write(&path, connection.to_string()).unwrap();
+        let kernel = Command::new(env!("CARGO_BIN_EXE_ubpp"))
+            .arg("kernel")
+            .arg(&path)
+            .spawn()
+            .unwrap();
+
+        let mut iopub = connect(ports[1], SocketType::Sub);
+        iopub.subscribe(b"").unwrap();
+        let mut client = Client {
+            kernel,
+            session: Session::new(KEY),
+            shell: connect(ports[0], SocketType::Dealer),
+            control: connect(ports[3], SocketType::Dealer),
+            stdin: connect(ports[2], SocketType::Dealer),
+            iopub,
+            hb: connect(ports[4], SocketType::Req),
+        };
+        client.wait_for_iopub();
+        client
+    }
+
+    /// Wie `jupyter_client`: Erst wenn eine Antwort auch auf IOPub ankommt, ist der Client bereit
+    fn wait_for_iopub(&mut self) {
+        self.iopub
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        'retry: for _ in 0..50 {
+            let request = self.send("kernel_info_request", json!({}));
+            recv(&mut self.shell, &self.session);
+            while let Ok(frames) = self.iopub.recv() {
+                let message = self.session.decode(frames).unwrap();
+                if message.parent_header["msg_id"] == request.header["msg_id"] {
+                    self.iopub_until_idle(&request);
+                    break 'retry;
+                }
+            }
+        }
+        self.iopub
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+    }
+
+    fn send(&mut self, msg_type: &str, content: Value) -> Message {
+        let request = self.session.message(msg_type, content, None);
+        let frames = self.session.encode(&request);
+        let socket = if msg_type == "shutdown_request" {
+            &mut self.control
+        } else {
+            &mut self.shell
+        };
+        socket.send(&frames).unwrap();
+        request
+    }
+
+    /// Die IOPub-Nachrichten zur Anfrage bis zum Status `idle`
+    fn iopub_until_idle(&mut self, request: &Message) -> Vec<Message> {
+        let mut messages = vec![];
+        loop {
+            let message = self.session.decode(self.iopub.recv().unwrap()).unwrap();
+            if message.parent_header["msg_id"] != request.header["msg_id"] {
+                continue;
+            }
+            if message.msg_type() == "status" && message.content["execution_state"] == "idle" {
+                return messages;
+            }
+            messages.push(message);
+        }
+    }
+
+    /// Führt eine Zelle aus und gibt die Antwort und die Ausgaben zurück
+    fn execute(&mut self, code: &str) -> (Message, Vec<Message>) {
+        let request = self.send(
+            "execute_request",
+            json!({ "code": code, "silent": false, "allow_stdin": false }),
+        );
+        let reply = recv(&mut self.shell, &self.session);
+        (reply, self.iopub_until_idle(&request))
+    }
+}
+
+fn recv(connection: &mut Connection, session: &Session) -> Message {
+    session.decode(connection.recv().unwrap()).unwrap()
+}
+
+fn find<'a>(messages: &'a [Message], msg_type: &str) -> &'a Message {
+    messages
+        .iter()
+        .find(|message| message.msg_type() == msg_type)
+        .unwrap_or_else(|| panic!("no {} in {:?}", msg_type, messages))
+}
+
+#[test]
+fn kernel_info_and_heartbeat() {
+    let mut client = Client::start("info");
+    client.send("kernel_info_request", json!({}));
+    let reply = recv(&mut client.shell, &client.session);
+    assert_eq!(reply.msg_type(), "kernel_info_reply");
+    assert_eq!(reply.content["language_info"]["name"], "ubpp");
+
+    client.hb.send(&[vec![], b"ping".to_vec()]).unwrap();
+    assert_eq!(client.hb.recv().unwrap(), vec![vec![], b"ping".to_vec()]);
+}
+
+#[test]
+fn state_persists_between_cells() {
+    let mut client = Client::start("state");
+    let (reply, _) = client.execute("loss x = 20;");
+    assert_eq!(reply.content["status"], "ok");
+
+    let (reply, outputs) = client.execute("gib us x plus 1;\nx mol 2");
+    assert_eq!(reply.content["status"], "ok");
+    assert_eq!(reply.content["execution_count"], 2);
+    let stream = find(&outputs, "stream");
+    assert_eq!(stream.content["name"], "stdout");
+    assert_eq!(stream.content["text"], "21\n");
+    let result = find(&outputs, "execute_result");
+    assert_eq!(result.content["data"]["text/plain"], "40");
+}
+
+#[test]
+fn errors_are_tracebacks() {
+    let mut client = Client::start("errors");
+    let (reply, outputs) = client.execute("gib us y;");
+    assert_eq!(reply.content["status"], "error");
+    assert_eq!(reply.content["ename"], "undefined");
+    let error = find(&outputs, "error");
+    assert_eq!(error.content["evalue"], "`y` not defined!");
+    assert!(error.content["traceback"][0]
+        .as_str()
+        .unwrap()
+        .contains("error: undefined"));
+
+    let (reply, _) = client.execute("loss = ;");
+    assert_eq!(reply.content["ename"], "parse");
+
+    client.send("is_complete_request", json!({ "code": "falls (wohr) {" }));
+    let reply = recv(&mut client.shell, &client.session);
+    assert_eq!(reply.content["status"], "incomplete");
+}
+
+#[test]
+fn input_uses_the_stdin_channel() {
+    let mut client = Client::start("input");
+    let request = client.send(
+        "execute_request",
+        json!({
+            "code": "loss name = trimm(e frog \"Wie heissisch?\"); gib us \"Hoi \" plus name;",
+            "silent": false,
+            "allow_stdin": true,
+        }),
+    );
+    let input_request = recv(&mut client.stdin, &client.session);
+    assert_eq!(input_request.msg_type(), "input_request");
+    assert_eq!(input_request.content["prompt"], "Wie heissisch?");
+    let input_reply = client.session.message(
+        "input_reply",
+        json!({ "value": "Anna" }),
+        Some(&input_request),
+    );
+    let frames = client.session.encode(&input_reply);
+    client.stdin.send(&frames).unwrap();
+
+    let reply = recv(&mut client.shell, &client.session);
+    assert_eq!(reply.content["status"], "ok");
+    let outputs = client.iopub_until_idle(&request);
+    assert_eq!(find(&outputs, "stream").content["text"], "Hoi Anna\n");
+}
+
+#[test]
+fn unsigned_messages_are_ignored_and_shutdown_stops_the_kernel() {
+    let mut client = Client::start("shutdown");
+    let forged = Session::new("falsch");
+    let frames = forged.encode(&forged.message("kernel_info_request", json!({}), None));
+    client.shell.send(&frames).unwrap();
+    let (reply, _) = client.execute("1 plus 1");
+    assert_eq!(reply.parent_header["msg_type"], "execute_request");
+
+    client.send("shutdown_request", json!({ "restart": false }));
+    let reply = recv(&mut client.control, &client.session);
+    assert_eq!(reply.msg_type(), "shutdown_reply");
+    assert!(client.kernel.wait().unwrap().success());
+}
+
+#[test]
+fn signature_is_hmac_sha256() {
+    let session = Session::new("key");
+    let signature = session.signature(&[b"The quick brown fox jumps over the lazy dog".to_vec()]);
+    assert_eq!(
+        String::from_utf8(signature).unwrap(),
+        "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+}
+
+#[test]
+fn messages_with_a_wrong_signature_are_rejected() {
+    let session = Session::new("key");
+    let message = session.message("kernel_info_request", json!({}), None);
+    let frames = session.encode(&message);
+    assert!(session.decode(frames.clone()).is_ok());
+
+    let signature = frames
+        .iter()
+        .position(|frame| frame == b"<IDS|MSG>")
+        .unwrap()
+        + 1;
+    for wrong in [
+        vec![],
+        b"00".repeat(32),
+        frames[signature][..63].to_vec(),
+        b"zz".repeat(32),
+    ] {
+        let mut frames = frames.clone();
+        frames[signature] = wrong;
+        assert_eq!(session.decode(frames).unwrap_err(), "Invalid signature");
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Namen, die mit einem Schlüsselwort beginnen, sind trotzdem Namen
+
+use ubpplib::{
+    dialect::Dialect,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+    testing::run_tests,
+};
+
+fn eval(source: &str) -> String {
+    let tokens = parse_file(source).unwrap();
+    eval_tokens(tokens, &mut Scope::default())
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn keywords_are_whole_words() {
+    assert_eq!(eval("loss tester = 2; tester"), "2");
+    assert_eq!(eval("loss erwartet = 3; erwartet plus 1"), "4");
+    assert_eq!(eval("loss nödig = wohr; nöd nödig"), "false");
+    assert_eq!(
+        eval("loss falsche = 1; loss lossi = 2; falsche plus lossi"),
+        "3"
+    );
+    assert_eq!(eval("loss odr1 = 4; odr1"), "4");
+    assert!(parse_file("loss falls = 1;").is_err());
+    assert!(parse_file("loss nöd = 1;").is_err());
+}
+
+#[test]
+fn test_keywords_are_names_outside_of_statements() {
+    assert_eq!(
+        eval("loss teste = 1; loss erwart = 2; teste plus erwart"),
+        "3"
+    );
+
+    let source = "loss teste = 1;
+        teste \"eis\" { erwart teste gliich isch wie 1; }
+        teste \"zwöi\" { erwart teste gliich isch wie 2; }";
+    let tokens = parse_file(source).unwrap();
+    let results = run_tests(&tokens, Scope::default);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].outcome.is_ok());
+    assert!(results[1].outcome.is_err());
+}
+
+#[test]
+fn english_test_is_a_name() {
+    let source = Dialect::English.translate("let test = 2;\ntest plus 1");
+    assert_eq!(eval(&source), "3");
+}
+
+#[test]
+fn function_and_match_keywords_are_whole_words() {
+    assert_eq!(eval("loss funktionen = 1; funktionen plus 1"), "2");
+    assert_eq!(eval("loss suscher = 2; suscher"), "2");
+    assert_eq!(eval("loss susch2 = 3; susch2"), "3");
+    assert_eq!(
+        eval("loss funktionen = 1; lueg ob funktionen { isch 1 => \"eis\", susch => \"anders\" }"),
+        "eis"
+    );
+    assert!(parse_file("loss funktion = 1;").is_err());
+    assert!(parse_file("loss susch = 1;").is_err());
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `lueg ob` mit Literalen, Bereichen und `susch`, als Anweisung und als Ausdruck
+
+use ubpplib::{
+    lint::lint,
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    let tokens = parse_file(source).unwrap();
+    eval_tokens(tokens, &mut Scope::default())
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+/// Ordnet `value` mit einem `lueg ob` Ausdruck ein
+fn grade(value: &str) -> String {
+    eval(&format!(
+        "lueg ob {} {{
+            isch 6 => \"super\",
+            isch 4 bis 5.5 => \"bestande\",
+            isch 1 odr 2 odr 3 => \"nöd bestande\",
+            isch \"a\" odr \"b\" => \"buechstabe\",
+            susch => \"komisch\",
+        }}",
+        value
+    ))
+    .unwrap()
+}
+
+#[test]
+fn patterns() {
+    assert_eq!(grade("6"), "super");
+    // Bereiche schliessen beide Grenzen ein
+    assert_eq!(grade("4"), "bestande");
+    assert_eq!(grade("4.75"), "bestande");
+    assert_eq!(grade("5.5"), "bestande");
+    assert_eq!(grade("2"), "nöd bestande");
+    assert_eq!(grade("\"b\""), "buechstabe");
+    assert_eq!(grade("7"), "komisch");
+    assert_eq!(grade("\"c\""), "komisch");
+}
+
+#[test]
+fn the_first_matching_arm_wins() {
+    assert_eq!(
+        eval("lueg ob 2 { isch 1 bis 3 => \"bereich\", isch 2 => \"zwei\", susch => \"nüt\" }"),
+        Ok("bereich".to_string())
+    );
+}
+
+#[test]
+fn statements_run_only_the_matching_arm() {
+    assert_eq!(
+        eval(
+            "loss treffer = \"\";
+            lueg ob 5 {
+                isch 1 => treffer = treffer plus \"eis\";,
+                isch 5 => { treffer = treffer plus \"föif\"; },
+                susch => treffer = treffer plus \"susch\";
+            }
+            treffer"
+        ),
+        Ok("föif".to_string())
+    );
+    // Ohne passenden Arm passiert nichts
+    assert_eq!(
+        eval("loss x = 1; lueg ob 9 { isch 1 => x = 2; } x"),
+        Ok("1".to_string())
+    );
+}
+
+#[test]
+fn arms_pass_on_errors_and_stop() {
+    assert_eq!(
+        eval("loss a = lueg ob 2 { isch 2 => { wirf \"im Arm\"; 1 }, susch => 0 };"),
+        Err(ErrorKind::Thrown)
+    );
+    assert_eq!(
+        eval(
+            "loss i = 0;
+            solang (i < 10) {
+                i = i plus 1;
+                lueg ob i { isch 3 => { stop; }, susch => {} }
+            }
+            i"
+        ),
+        Ok("3".to_string())
+    );
+}
+
+#[test]
+fn missing_default_arm_is_a_warning() {
+    let warnings = |source: &str| lint(&parse_file(source).unwrap());
+    assert_eq!(
+        warnings("lueg ob 1 { isch 1 => gib us 1; }"),
+        ["`lueg ob` ohni `susch` Arm"]
+    );
+    assert_eq!(
+        warnings("loss x = lueg ob 1 { isch 1 => \"eis\" };"),
+        ["`lueg ob` ohni `susch` Arm"]
+    );
+    assert!(warnings("lueg ob 1 { isch 1 => gib us 1;, susch => gib us 2; }").is_empty());
+}
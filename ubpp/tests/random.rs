@@ -0,0 +1,56 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+fn eval_seeded(source: &str, seed: u64) -> String {
+    let tokens = parse_file(source).unwrap();
+    let mut scope = Scope::default();
+    scope.seed = Some(seed);
+    eval_tokens(tokens, &mut scope).unwrap().to_string()
+}
+
+const DICE: &str = "zuefallszahl zwüsche 1 und 6 plus \" \" plus zuefallszahl zwüsche 1 und 6 \
+                    plus \" \" plus mische(teile(\"abcdef\", \"\")) \
+                    plus \" \" plus zuefallswahl(teile(\"Stei,Schäri,Papier\", \",\"))";
+
+#[test]
+fn same_seed_same_result() {
+    assert_eq!(eval_seeded(DICE, 42), eval_seeded(DICE, 42));
+    assert_eq!(eval_seeded(DICE, 7), eval_seeded(DICE, 7));
+}
+
+/// Der Generator ist fest vorgegeben, ein Seed gibt auch nach einem Update die gleichen Zahlen
+#[test]
+fn seeded_results_are_stable() {
+    assert_eq!(eval_seeded("zuefallszahl zwüsche 1 und 1000", 42), "682");
+    assert_eq!(
+        eval_seeded("verbinde(mische(teile(\"abcdef\", \"\")), \"\")", 42),
+        "cbfeda"
+    );
+}
+
+#[test]
+fn random_number_stays_in_range() {
+    for seed in 0..50 {
+        let n: i64 = eval_seeded("zuefallszahl zwüsche -2 und 2", seed)
+            .parse()
+            .unwrap();
+        assert!((-2..=2).contains(&n));
+    }
+    assert_eq!(eval_seeded("zuefallszahl zwüsche 3 und 3", 1), "3");
+}
+
+#[test]
+fn shuffle_keeps_elements() {
+    let shuffled = eval_seeded("verbinde(mische(teile(\"abcdef\", \"\")), \"\")", 3);
+    let mut chars = shuffled.chars().collect::<Vec<_>>();
+    chars.sort();
+    assert_eq!(chars.into_iter().collect::<String>(), "abcdef");
+}
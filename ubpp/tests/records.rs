@@ -0,0 +1,88 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod common;
+
+use common::eval_with_definitions as eval;
+use ubpplib::{step1::ErrorKind, step2::parse_file};
+
+#[test]
+fn construction_and_field_access() {
+    assert_eq!(
+        eval("Person(\"Anna\", 12)"),
+        Ok("Person { name: Anna, alter: 12 }".to_string())
+    );
+    assert_eq!(
+        eval("loss p = Person(\"Anna\", 12); p.alter"),
+        Ok("12".to_string())
+    );
+    assert_eq!(eval("Person(\"Anna\")"), Err(ErrorKind::Argument));
+    assert_eq!(
+        eval("loss p = Person(\"Anna\", 12); p.grösse"),
+        Err(ErrorKind::Type)
+    );
+}
+
+#[test]
+fn field_assignment_copies_the_record() {
+    assert_eq!(
+        eval("loss p = Person(\"Anna\", 12); p.alter = p.alter plus 1; p.alter"),
+        Ok("13".to_string())
+    );
+    assert_eq!(
+        eval("loss p = Person(\"Anna\", 12); loss q = p; q.name = \"Bea\"; p.name"),
+        Ok("Anna".to_string())
+    );
+    assert_eq!(
+        eval("loss p = Person(\"Anna\", 12); p.grösse = 170;"),
+        Err(ErrorKind::Type)
+    );
+    assert_eq!(eval("x.name = 1;"), Err(ErrorKind::Undefined));
+}
+
+#[test]
+fn structural_equality() {
+    assert_eq!(
+        eval("Person(\"Anna\", 12) gliich isch wie Person(\"Anna\", 12.0)"),
+        Ok("true".to_string())
+    );
+    assert_eq!(
+        eval("Person(\"Anna\", 12) == Person(\"Anna\", 13)"),
+        Ok("false".to_string())
+    );
+    assert_eq!(
+        eval(
+            "definier e struktur wo Tier heisst mit name, alter;
+            Person(\"Rex\", 3) == Tier(\"Rex\", 3)"
+        ),
+        Ok("false".to_string())
+    );
+}
+
+#[test]
+fn records_have_no_order() {
+    for source in [
+        "Person(\"Anna\", 12) < Person(\"Bea\", 12)",
+        "Person(\"Anna\", 12) <= Person(\"Anna\", 12)",
+        "Person(\"Anna\", 12) isch grösser als 1",
+        "1 >= Person(\"Anna\", 12)",
+    ] {
+        assert_eq!(eval(source), Err(ErrorKind::Type), "{}", source);
+    }
+    assert_eq!(
+        eval("Person(\"Anna\", 12) != Person(\"Bea\", 12)"),
+        Ok("true".to_string())
+    );
+}
+
+#[test]
+fn structs_are_defined_once() {
+    assert_eq!(
+        eval("definier e struktur wo Person heisst mit x;"),
+        Err(ErrorKind::AlreadyDefined)
+    );
+    assert!(parse_file("definier e struktur wo P heisst mit x, x;").is_err());
+}
@@ -0,0 +1,120 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    resolve::resolve,
+    step1::{ErrorKind, RuntimeError},
+    step2::parse_file,
+    step3::{eval_program, eval_tokens, Scope},
+};
+
+fn eval_in(scope: &mut Scope, source: &str) -> Result<String, RuntimeError> {
+    let tokens = parse_file(source).unwrap();
+    eval_tokens(tokens, scope).map(|result| result.to_string())
+}
+
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    eval_in(&mut Scope::default(), source).map_err(|e| e.kind)
+}
+
+#[test]
+fn undefined_names_are_reported_before_running() {
+    let tokens = parse_file("gib us \"vorher\"; gib us unbekannt;").unwrap();
+    let error = resolve(tokens, &mut Scope::default()).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Undefined);
+    assert_eq!(error.message, "`unbekannt` not defined!");
+
+    assert_eq!(
+        eval("falls (falsch) { gib us y; }"),
+        Err(ErrorKind::Undefined)
+    );
+    assert_eq!(eval("funktion () { y }"), Err(ErrorKind::Undefined));
+    assert_eq!(
+        eval("lueg ob 1 { isch Kreis => 1 }"),
+        Err(ErrorKind::Undefined)
+    );
+}
+
+#[test]
+fn a_program_is_resolved_once() {
+    let tokens = parse_file("loss x = 1; x plus 1").unwrap();
+    let program = resolve(tokens, &mut Scope::default()).unwrap();
+    let mut scope = Scope::default();
+    assert_eq!(eval_program(&program, &mut scope).unwrap().to_string(), "2");
+    assert_eq!(
+        eval_program(&program, &mut scope).unwrap_err().kind,
+        ErrorKind::AlreadyDefined
+    );
+    assert_eq!(
+        eval_program(&program, &mut Scope::default())
+            .unwrap()
+            .to_string(),
+        "2"
+    );
+    // In einem Scope, in dem `x` schon einen anderen Slot hat, wird eine Kopie neu aufgelöst
+    let mut other = Scope::default();
+    eval_in(&mut other, "loss y = 5; loss x = 0;").unwrap();
+    assert_eq!(
+        eval_program(&program, &mut other).unwrap_err().kind,
+        ErrorKind::AlreadyDefined
+    );
+    assert_eq!(eval_in(&mut other, "x plus y").unwrap(), "5");
+}
+
+#[test]
+fn a_definition_anywhere_is_enough() {
+    // Ob die Definition auch ausgeführt wird, zeigt sich erst zur Laufzeit
+    assert_eq!(
+        eval("falls (falsch) { loss y = 1; } y"),
+        Err(ErrorKind::Undefined)
+    );
+    // Funktionen sehen nur Variablen, die bei ihrer Definition schon einen Wert haben
+    assert_eq!(
+        eval("loss f = funktion () { y }; loss y = 2; f()"),
+        Err(ErrorKind::Undefined)
+    );
+    assert_eq!(
+        eval("probier { wirf 1; } fang fehler { } fehler"),
+        Err(ErrorKind::Undefined)
+    );
+    assert_eq!(
+        eval("definier e struktur wo P heisst mit x; loss p = P(1); p.x"),
+        Ok("1".to_string())
+    );
+}
+
+#[test]
+fn variables_shadow_builtins() {
+    assert_eq!(eval("max(1, 2)"), Ok("2".to_string()));
+    assert_eq!(
+        eval("loss a = max(1, 2); loss max = 10; a plus max"),
+        Ok("12".to_string())
+    );
+    assert_eq!(
+        eval("loss f = funktion (pi) { pi }; f(2) mol 10 plus rund(pi)"),
+        Ok("23".to_string())
+    );
+}
+
+#[test]
+fn later_programs_see_earlier_variables() {
+    // Wie Zellen im Notebook
+    let mut scope = Scope::default();
+    let mut eval = |source| eval_in(&mut scope, source).map_err(|e| e.kind);
+    assert_eq!(eval("loss x = 20;"), Ok("null".to_string()));
+    assert_eq!(eval("x mol 2"), Ok("40".to_string()));
+    assert_eq!(
+        eval("loss f = funktion () { x plus y };"),
+        Err(ErrorKind::Undefined)
+    );
+    assert_eq!(
+        eval("loss y = 1; loss f = funktion () { x plus y }; f()"),
+        Ok("21".to_string())
+    );
+    let mut names = scope.variable_names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["f", "x", "y"]);
+}
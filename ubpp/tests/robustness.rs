@@ -0,0 +1,165 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Eingaben, die früher zu einem Absturz geführt haben, und ein kleiner Fuzzer für `cargo test`.
+//! Der richtige Fuzzer liegt in `fuzz/`.
+
+use std::path::Path;
+
+use ubpplib::{
+    step1::{ErrorKind, Token},
+    step2::{parse_file, parse_file_with_nesting, MAX_NESTING},
+    step3::{eval_tokens, Scope},
+};
+
+/// Das Programm muss gültig sein, ein Parse-Fehler lässt den Test scheitern
+fn eval(source: &str) -> Result<String, ErrorKind> {
+    run(parse_file(source).unwrap())
+}
+
+fn run(tokens: Vec<Token>) -> Result<String, ErrorKind> {
+    let mut scope = Scope::default();
+    scope.step_limit = Some(1_000);
+    eval_tokens(tokens, &mut scope)
+        .map(|result| result.to_string())
+        .map_err(|e| e.kind)
+}
+
+#[test]
+fn unicode_digits_are_a_parse_error() {
+    assert!(parse_file("loss x = ٣;").is_err());
+    assert!(parse_file("loss x = ½;").is_err());
+    assert_eq!(eval("loss x = 3; x"), Ok("3".to_string()));
+}
+
+#[test]
+fn huge_arguments_are_errors() {
+    assert_eq!(
+        eval("wiederhol(\"ab\", 9999999999999999999)"),
+        Err(ErrorKind::Argument)
+    );
+    assert_eq!(
+        eval("usschnitt(\"abc\", 1, 18446744073709551615)"),
+        Err(ErrorKind::Index)
+    );
+    assert_eq!(eval("2 hoch 4000000000"), Err(ErrorKind::Arithmetic));
+    assert_eq!(eval("(-3) hoch 1000000"), Err(ErrorKind::Arithmetic));
+    assert_eq!(eval("1 hoch 4000000000"), Ok("1".to_string()));
+    assert_eq!(eval("(-1) hoch 4000000001"), Ok("-1".to_string()));
+    assert_eq!(
+        eval("längi(2 hoch 500000 als Zeicheketti)"),
+        Ok("150515".to_string())
+    );
+}
+
+#[test]
+fn step_limit_stops_endless_loops() {
+    assert_eq!(eval("solang de wohr mach {}"), Err(ErrorKind::Limit));
+    assert_eq!(
+        eval("loss i = 0; solang de wohr mach { i = i plus 1; }"),
+        Err(ErrorKind::Limit)
+    );
+    // Das Limit lässt sich nicht mit `probier` umgehen
+    assert_eq!(
+        eval("probier { solang de wohr mach {} } fang f { gib us f; }"),
+        Err(ErrorKind::Limit)
+    );
+    assert_eq!(
+        eval("loss i = 0; solang de i < 10 mach { i = i plus 1; } i"),
+        Ok("10".to_string())
+    );
+}
+
+#[test]
+fn deep_nesting_is_a_parse_error() {
+    let parentheses = format!("gib us {}1{};", "(".repeat(10_000), ")".repeat(10_000));
+    assert!(parse_file(&parentheses).is_err());
+    let blocks = |depth| {
+        format!(
+            "{}gib us 1;{}",
+            "falls (wohr) {".repeat(depth),
+            "}".repeat(depth)
+        )
+    };
+    let error = parse_file(&blocks(MAX_NESTING + 1)).unwrap_err();
+    assert!(error.to_string().contains("nested deeper than"));
+    assert!(parse_file(&format!("gib us {}1;", "nöd ".repeat(MAX_NESTING + 1))).is_err());
+
+    let error = parse_file_with_nesting(&blocks(10), 10).unwrap_err();
+    assert!(error.to_string().contains("nested deeper than 10 levels"));
+    assert!(parse_file_with_nesting(&blocks(MAX_NESTING + 1), MAX_NESTING + 10).is_ok());
+}
+
+/// Jeder Block wird nur einmal geparst, früher brauchte jede Ebene doppelt so lange
+#[test]
+fn nested_blocks_up_to_the_limit_work() {
+    let depth = MAX_NESTING / 2 - 1;
+    let statements = format!(
+        "{}stop;{}",
+        "solang (wohr) {".repeat(depth),
+        "stop; }".repeat(depth)
+    );
+    assert_eq!(eval(&statements), Ok("null".to_string()));
+    let value = format!(
+        "{}1{}",
+        "falls (wohr) { ".repeat(depth),
+        " } suscht { 2 }".repeat(depth)
+    );
+    assert_eq!(eval(&value), Ok("1".to_string()));
+    let functions = format!(
+        "loss f = {}1{}; f",
+        "funktion () { ".repeat(depth),
+        " }".repeat(depth)
+    );
+    assert!(eval(&functions).is_ok());
+}
+
+#[test]
+fn depth_limit_stops_endless_recursion() {
+    let run = |source: &str, depth_limit| {
+        let tokens = parse_file(source).unwrap();
+        let mut scope = Scope::default();
+        scope.depth_limit = depth_limit;
+        eval_tokens(tokens, &mut scope)
+            .map(|result| result.to_string())
+            .map_err(|e| e.kind)
+    };
+    let count = "loss f = funktion (n) { falls (n < 1) { 0 } suscht { f(n minus 1) plus 1 } };";
+    assert_eq!(
+        run(&format!("{} f(1000)", count), None),
+        Ok("1000".to_string())
+    );
+    assert_eq!(
+        run(&format!("{} f(1000)", count), Some(100)),
+        Err(ErrorKind::Limit)
+    );
+    assert_eq!(
+        run("loss f = funktion (n) { f(n plus 1) }; f(0)", None),
+        Err(ErrorKind::Limit)
+    );
+}
+
+/// Ein abgeschnittenes Programm ist meistens ungültig und muss trotzdem sauber scheitern
+#[test]
+fn truncated_programs_do_not_panic() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "ubpp") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).unwrap();
+        // `e frog` würde auf die Standardeingabe warten
+        if source.contains("e frog") {
+            continue;
+        }
+        for (end, _) in source.match_indices(['\n', '(']) {
+            if let Ok(tokens) = parse_file(&source[..end]) {
+                let _ = run(tokens);
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+    testing::run_tests,
+};
+
+const SOURCE: &str = r#"
+loss grenze = 10;
+teste "bestande" {
+    erwart 1 + 1 gliich isch wie 2;
+    erwart grenze isch grösser als 5;
+    grenze = 0;
+}
+teste "frische scope" {
+    erwart grenze gliich isch wie 10;
+}
+teste "nöd bestande" {
+    erwart 3 mol 2 gliich isch wie 7;
+}
+"#;
+
+#[test]
+fn reports_each_test() {
+    let tokens = parse_file(SOURCE).unwrap();
+    let results = run_tests(&tokens, Scope::default);
+    let summary = results
+        .iter()
+        .map(|result| (result.name.as_str(), result.line, result.outcome.is_ok()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("bestande", 3, true),
+            ("frische scope", 8, true),
+            ("nöd bestande", 11, false)
+        ]
+    );
+    let error = results[2].outcome.as_ref().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Expectation);
+    assert_eq!(error.message, "line 12: expected `7`, got `6`");
+}
+
+#[test]
+fn tests_are_skipped_in_normal_runs() {
+    let tokens = parse_file(SOURCE).unwrap();
+    assert!(eval_tokens(tokens, &mut Scope::default()).is_ok());
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::path::Path;
+
+use serde_json::Value;
+use ubpplib::textmate::textmate_grammar;
+
+fn generated() -> String {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    textmate_grammar(&std::fs::read_to_string(root.join("ubpp.pest")).unwrap()).unwrap()
+}
+
+#[test]
+fn checked_in_grammar_is_up_to_date() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../ubpp-syntax/syntaxes/ubpp.tmLanguage.json");
+    let checked_in = std::fs::read_to_string(path).unwrap();
+    assert!(
+        checked_in == generated(),
+        "ubpp.tmLanguage.json is out of date, run `cargo run --bin textmate`"
+    );
+}
+
+/// Alle `match` Ausdrücke einer Gruppe aus dem `repository`
+fn matches(grammar: &Value, group: &str) -> String {
+    grammar["repository"][group]["patterns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pattern| pattern["match"].as_str().unwrap())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn grammar_contains_keywords_operators_and_types() {
+    let grammar: Value = serde_json::from_str(&generated()).unwrap();
+    let functions = matches(&grammar, "known-functions");
+    for word in ["gib us", "e frog", "lies datei"] {
+        assert!(
+            functions.contains(word),
+            "{} missing in {}",
+            word,
+            functions
+        );
+    }
+    let operators = matches(&grammar, "operators");
+    for word in [
+        "plus",
+        "mol",
+        "hoch",
+        "isch grösser oder gliich wie",
+        "kliiner isch als",
+        "\\*\\*",
+    ] {
+        assert!(
+            operators.contains(word),
+            "{} missing in {}",
+            word,
+            operators
+        );
+    }
+    let keywords = matches(&grammar, "keywords");
+    for word in ["falls", "definier e uswahl wo", "funktion", "bis"] {
+        assert!(keywords.contains(word), "{} missing in {}", word, keywords);
+    }
+    let types = matches(&grammar, "predefined-types");
+    for word in ["Zahl", "Zeicheketti"] {
+        assert!(types.contains(word), "{} missing in {}", word, types);
+    }
+}
+
+#[test]
+fn unknown_rules_are_an_error() {
+    let error = textmate_grammar("plus = { \"plus\" }").unwrap_err();
+    assert!(error.contains("Unknown rule"), "{}", error);
+}
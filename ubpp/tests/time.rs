@@ -0,0 +1,92 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ubpplib::{
+    builtins::parse_date,
+    step1::ErrorKind,
+    step2::parse_file,
+    step3::{eval_tokens, Scope},
+};
+
+/// Wertet `source` aus, wobei `jetzt()` immer den 18.10.2026 um 14:30 zurückgibt
+fn eval(source: &str) -> String {
+    let tokens = parse_file(source).unwrap();
+    let mut scope = Scope::default();
+    scope.now = Some(parse_date("18.10.2026 14:30").unwrap());
+    eval_tokens(tokens, &mut scope).unwrap().to_string()
+}
+
+#[test]
+fn mocked_now() {
+    assert_eq!(eval("jetzt()"), "18.10.2026 14:30");
+    assert_eq!(eval("hüt()"), "18.10.2026");
+    assert_eq!(eval("wuchetag(jetzt())"), "Sunntig");
+}
+
+#[test]
+fn date_arithmetic() {
+    assert_eq!(eval("tägDezue(datum(\"18.10.2026\"), 1)"), "19.10.2026");
+    assert_eq!(eval("wuchetag(tägDezue(hüt(), 2))"), "Ziischtig");
+    assert_eq!(eval("tägZwüsche(hüt(), datum(\"25.12.2026\"))"), "68");
+    assert_eq!(eval("tägZwüsche(datum(\"25.12.2026\"), hüt())"), "-68");
+    assert_eq!(
+        eval("formatiere(jetzt(), \"%Y-%m-%d %H:%M\")"),
+        "2026-10-18 14:30"
+    );
+}
+
+#[test]
+fn formats_use_swiss_names() {
+    assert_eq!(
+        eval("formatiere(jetzt(), \"%A, %e. %B %Y\")"),
+        "Sunntig, 18. Oktober 2026"
+    );
+    assert_eq!(
+        eval("formatiere(datum(\"03.08.2026\"), \"%a %-d. %b / %h\")"),
+        "Mä 3. Aug / Aug"
+    );
+    assert_eq!(
+        eval("formatiere(jetzt(), \"%c | %v\")"),
+        "Su Okt 18 14:30:00 2026 | 18-Okt-2026"
+    );
+    // `%%A` ist ein `%` gefolgt von `A`
+    assert_eq!(eval("formatiere(jetzt(), \"%%A %%%A\")"), "%A %Sunntig");
+}
+
+#[test]
+fn strict_mode_wants_dates() {
+    let mut scope = Scope::default();
+    scope.strict = true;
+    let tokens = parse_file("wuchetag(\"18.10.2026\")").unwrap();
+    let error = eval_tokens(tokens, &mut scope).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Type);
+    let tokens = parse_file("tägZwüsche(datum(\"18.10.2026\"), datum(\"20.10.2026\"))").unwrap();
+    assert_eq!(eval_tokens(tokens, &mut scope).unwrap().to_string(), "2");
+    assert_eq!(eval("wuchetag(\"18.10.2026\")"), "Sunntig");
+}
+
+#[test]
+fn invalid_formats_are_errors() {
+    // Ein Datum hat keine Zeitzone, `%z` darf trotzdem nicht abstürzen
+    for format in ["%z", "%Z", "%:z", "%Q", "%", "%-"] {
+        let source = format!(
+            "loss art = \"\"; probier {{ formatiere(jetzt(), \"{}\"); }} fang f {{ art = f.art; }} art",
+            format
+        );
+        assert_eq!(eval(&source), "argument", "{}", format);
+    }
+}
+
+#[test]
+fn date_comparison() {
+    assert_eq!(eval("hüt() isch kliiner als jetzt()"), "true");
+    assert_eq!(eval("datum(\"01.01.2027\") > hüt()"), "true");
+    assert_eq!(eval("datum(\"18.10.2026\") gliich isch wie hüt()"), "true");
+    // Ein Datum lässt sich nur mit einem Datum vergleichen, nicht mit seinem Text
+    let source = "loss art = \"\"; probier { hüt() gliich isch wie \"2026-10-18 00:00:00\"; } \
+                  fang f { art = f.art; } art";
+    assert_eq!(eval(source), "type");
+}
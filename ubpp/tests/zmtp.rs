@@ -0,0 +1,73 @@
+// Copyright (c) 2022 Ubique Innovation AG <https://www.ubique.ch>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Spricht ZMTP von Hand mit einer [`Connection`], um auch ungültige Frames zu schicken
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+
+use ubpplib::kernel::zmtp::{Connection, SocketType, MAX_FRAME_SIZE};
+
+/// Antwortet als Server auf den Handshake und schickt danach `bytes`. Die Verbindung bleibt
+/// offen, bis die Gegenstelle sie schliesst.
+fn serve_raw(bytes: Vec<u8>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut greeting = [0u8; 64];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        greeting[32] = 1;
+        stream.write_all(&greeting).unwrap();
+        stream.read_exact(&mut greeting).unwrap();
+        // READY ohne Eigenschaften, danach der READY Befehl des Clients
+        stream.write_all(b"\x04\x06\x05READY").unwrap();
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).unwrap();
+        stream.read_exact(&mut vec![0; header[1] as usize]).unwrap();
+        stream.write_all(&bytes).unwrap();
+        let _ = stream.read(&mut [0]);
+    });
+    port
+}
+
+fn connect(port: u16) -> Connection {
+    let connection = Connection::connect(("127.0.0.1", port), SocketType::Dealer, b"").unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    connection
+}
+
+#[test]
+fn frames_are_received() {
+    let port = serve_raw(b"\x00\x05hallo".to_vec());
+    assert_eq!(connect(port).recv().unwrap(), [b"hallo".to_vec()]);
+}
+
+#[test]
+fn oversized_frames_are_rejected_before_reading_them() {
+    // Nur die Länge, ohne Inhalt. Wartete `recv` auf den Inhalt, gäbe es einen Timeout.
+    let mut header = vec![0x02];
+    header.extend((MAX_FRAME_SIZE + 1).to_be_bytes());
+    let port = serve_raw(header);
+    let error = connect(port).recv().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // So viel Speicher liesse sich gar nicht reservieren
+    let mut header = vec![0x02];
+    header.extend(u64::MAX.to_be_bytes());
+    let port = serve_raw(header);
+    let error = connect(port).recv().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}